@@ -1,22 +1,12 @@
-use rdev::listen;
-use std::sync::mpsc::channel;
-use std::thread;
+use rdev::listen_channel;
 
 fn main() {
-    // spawn new thread because listen blocks
-    let (schan, rchan) = channel();
-    let _listener = thread::spawn(move || {
-        listen(move |event| {
-            schan
-                .send(event)
-                .unwrap_or_else(|e| println!("Could not send event {:?}", e));
-        })
-        .expect("Could not listen");
-    });
+    // The listener runs on a background thread until `events` is dropped.
+    let events = listen_channel();
 
-    let mut events = Vec::new();
-    for event in rchan.iter() {
+    let mut received = Vec::new();
+    for event in events {
         println!("Received {:?}", event);
-        events.push(event);
+        received.push(event);
     }
 }
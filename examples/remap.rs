@@ -0,0 +1,23 @@
+use rdev::{Event, EventType, Key, grab};
+
+fn main() {
+    // This will block.
+    if let Err(error) = grab(callback) {
+        println!("Error: {:?}", error)
+    }
+}
+
+// Swaps CapsLock and Escape (Linux only, other platforms ignore the modification).
+fn callback(event: Event) -> Option<Event> {
+    let event_type = match event.event_type {
+        EventType::KeyPress(Key::CapsLock) => EventType::KeyPress(Key::Escape),
+        EventType::KeyRelease(Key::CapsLock) => EventType::KeyRelease(Key::Escape),
+        EventType::KeyPress(Key::Escape) => EventType::KeyPress(Key::CapsLock),
+        EventType::KeyRelease(Key::Escape) => EventType::KeyRelease(Key::CapsLock),
+        event_type => event_type,
+    };
+    Some(Event {
+        event_type,
+        ..event
+    })
+}
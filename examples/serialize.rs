@@ -1,11 +1,16 @@
-use rdev::{Event, EventType, Key};
+use rdev::{Event, EventSource, EventType, Key, Modifiers, RawCode};
 use std::time::SystemTime;
 
 fn main() {
     let event = Event {
         event_type: EventType::KeyPress(Key::KeyS),
         time: SystemTime::now(),
+        timestamp: None,
         name: Some(String::from("S")),
+        device: None,
+        source: EventSource::Hardware,
+        modifiers: Modifiers::SHIFT,
+        raw_code: Some(RawCode::Evdev(31)),
     };
 
     let serialized = serde_json::to_string(&event).unwrap();
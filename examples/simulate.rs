@@ -1,4 +1,4 @@
-use rdev::{Button, EventType, Key, SimulateError, simulate};
+use rdev::{Button, EventType, Key, simulate};
 use std::{
     thread,
     time::{self, Duration},
@@ -8,8 +8,8 @@ fn send(event_type: &EventType) {
     let delay = time::Duration::from_millis(20);
     match simulate(event_type) {
         Ok(()) => (),
-        Err(SimulateError) => {
-            println!("We could not send {:?}", event_type);
+        Err(error) => {
+            println!("We could not send {:?}: {}", event_type, error);
         }
     }
     // Let ths OS catchup (at least MacOS)
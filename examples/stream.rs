@@ -0,0 +1,22 @@
+use futures::StreamExt;
+use rdev::listen_stream;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    let mut events = listen_stream();
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(event) => println!("Received {:?}", event),
+                None => break,
+            },
+            _ = ticks.tick() => println!("Still listening"),
+        }
+    }
+    if let Err(error) = events.stop() {
+        println!("Error: {:?}", error);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::rdev::{Event, ListenError, ListenHandle};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError, channel};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Receiving end of `listen_channel`. Events are buffered until they are
+/// read, iterating blocks until the next event arrives.
+/// Dropping the receiver stops the background listener.
+///
+/// ```no_run
+/// use rdev::listen_channel;
+///
+/// let events = listen_channel();
+/// for event in events {
+///     println!("Received {:?}", event);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EventReceiver {
+    receiver: Receiver<Event>,
+    handle: ListenHandle,
+    thread: Option<JoinHandle<Result<(), ListenError>>>,
+}
+
+impl EventReceiver {
+    /// Blocks until the next event. Returns `None` if the listener stopped,
+    /// use `stop` to get the reason.
+    pub fn recv(&self) -> Option<Event> {
+        self.receiver.recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Event, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    pub fn try_recv(&self) -> Result<Event, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Stops the background listener and waits for it to finish.
+    /// Returns the error of the listener if it could not start.
+    pub fn stop(mut self) -> Result<(), ListenError> {
+        self.handle.stop();
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            // The listener thread panicked, there is no `ListenError` to report.
+            Some(Err(_)) | None => Ok(()),
+        }
+    }
+}
+
+impl Iterator for EventReceiver {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.recv()
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.handle.stop();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Listens to global events on a background thread, and returns a receiver
+/// for those events. This is the same as `listen` with a callback sending to a
+/// channel, without needing a `'static` sender.
+/// Caveat: On MacOS, the listener does not run on the main thread so
+/// `set_is_main_thread(false)` needs to be called first.
+///
+/// ```no_run
+/// use rdev::listen_channel;
+/// use std::time::Duration;
+///
+/// let events = listen_channel();
+/// while let Ok(event) = events.recv_timeout(Duration::from_secs(5)) {
+///     println!("Received {:?}", event);
+/// }
+/// // Dropping `events` stops the listener.
+/// ```
+pub fn listen_channel() -> EventReceiver {
+    let (sender, receiver) = channel();
+    let handle = ListenHandle::new();
+    let listen_handle = handle.clone();
+    let thread = thread::spawn(move || {
+        crate::listen_until(
+            move |event| {
+                // The receiver is gone, the handle is stopped in `drop` too.
+                sender.send(event).ok();
+            },
+            &listen_handle,
+        )
+    });
+    EventReceiver {
+        receiver,
+        handle,
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,235 @@
+use crate::rdev::Key;
+
+/// Usages are written `page << 16 | id` like in Chromium's tables: most keys are on the
+/// keyboard page (0x07), media and browser keys on the consumer page (0x0C), and
+/// Sleep and Wake on the generic desktop page (0x01).
+macro_rules! decl_codes {
+    ($($key:ident, $usage:literal, $code:literal),*) => {
+        impl Key {
+            /// USB HID usage of the key as `page << 16 | id`, e.g. `0x070004` for `KeyA`.
+            /// `None` for `Function`, which has no standard usage, and `Unknown` keys.
+            pub const fn to_hid_usage(self) -> Option<u32> {
+                match self {
+                    $(
+                        Key::$key => Some($usage),
+                    )*
+                    Key::Function | Key::Unknown(_) => None,
+                }
+            }
+
+            /// Key of a USB HID usage written `page << 16 | id`.
+            pub const fn from_hid_usage(usage: u32) -> Option<Key> {
+                match usage {
+                    $(
+                        $usage => Some(Key::$key),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// W3C UI Events `KeyboardEvent.code` of the key, e.g. `"NumpadEnter"` for `KpReturn`.
+            /// `None` for `Unknown` keys.
+            pub const fn to_w3c_code(self) -> Option<&'static str> {
+                match self {
+                    $(
+                        Key::$key => Some($code),
+                    )*
+                    Key::Function => Some("Fn"),
+                    Key::Unknown(_) => None,
+                }
+            }
+
+            /// Key of a W3C UI Events `KeyboardEvent.code`, codes are case sensitive.
+            /// The legacy `OSLeft` and `OSRight` codes are the Meta keys.
+            pub fn from_w3c_code(code: &str) -> Option<Key> {
+                match code {
+                    $(
+                        $code => Some(Key::$key),
+                    )*
+                    "Fn" => Some(Key::Function),
+                    "OSLeft" => Some(Key::MetaLeft),
+                    "OSRight" => Some(Key::MetaRight),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+decl_codes!(
+    Alt, 0x0700E2, "AltLeft",
+    AltGr, 0x0700E6, "AltRight",
+    Backspace, 0x07002A, "Backspace",
+    CapsLock, 0x070039, "CapsLock",
+    ControlLeft, 0x0700E0, "ControlLeft",
+    ControlRight, 0x0700E4, "ControlRight",
+    Delete, 0x07004C, "Delete",
+    DownArrow, 0x070051, "ArrowDown",
+    End, 0x07004D, "End",
+    Escape, 0x070029, "Escape",
+    F1, 0x07003A, "F1",
+    F10, 0x070043, "F10",
+    F11, 0x070044, "F11",
+    F12, 0x070045, "F12",
+    F13, 0x070068, "F13",
+    F14, 0x070069, "F14",
+    F15, 0x07006A, "F15",
+    F16, 0x07006B, "F16",
+    F17, 0x07006C, "F17",
+    F18, 0x07006D, "F18",
+    F19, 0x07006E, "F19",
+    F20, 0x07006F, "F20",
+    F21, 0x070070, "F21",
+    F22, 0x070071, "F22",
+    F23, 0x070072, "F23",
+    F24, 0x070073, "F24",
+    F2, 0x07003B, "F2",
+    F3, 0x07003C, "F3",
+    F4, 0x07003D, "F4",
+    F5, 0x07003E, "F5",
+    F6, 0x07003F, "F6",
+    F7, 0x070040, "F7",
+    F8, 0x070041, "F8",
+    F9, 0x070042, "F9",
+    Home, 0x07004A, "Home",
+    LeftArrow, 0x070050, "ArrowLeft",
+    MetaLeft, 0x0700E3, "MetaLeft",
+    MetaRight, 0x0700E7, "MetaRight",
+    PageDown, 0x07004E, "PageDown",
+    PageUp, 0x07004B, "PageUp",
+    Return, 0x070028, "Enter",
+    RightArrow, 0x07004F, "ArrowRight",
+    ShiftLeft, 0x0700E1, "ShiftLeft",
+    ShiftRight, 0x0700E5, "ShiftRight",
+    Space, 0x07002C, "Space",
+    Tab, 0x07002B, "Tab",
+    UpArrow, 0x070052, "ArrowUp",
+    PrintScreen, 0x070046, "PrintScreen",
+    ScrollLock, 0x070047, "ScrollLock",
+    Pause, 0x070048, "Pause",
+    NumLock, 0x070053, "NumLock",
+    BackQuote, 0x070035, "Backquote",
+    Num1, 0x07001E, "Digit1",
+    Num2, 0x07001F, "Digit2",
+    Num3, 0x070020, "Digit3",
+    Num4, 0x070021, "Digit4",
+    Num5, 0x070022, "Digit5",
+    Num6, 0x070023, "Digit6",
+    Num7, 0x070024, "Digit7",
+    Num8, 0x070025, "Digit8",
+    Num9, 0x070026, "Digit9",
+    Num0, 0x070027, "Digit0",
+    Minus, 0x07002D, "Minus",
+    Equal, 0x07002E, "Equal",
+    KeyQ, 0x070014, "KeyQ",
+    KeyW, 0x07001A, "KeyW",
+    KeyE, 0x070008, "KeyE",
+    KeyR, 0x070015, "KeyR",
+    KeyT, 0x070017, "KeyT",
+    KeyY, 0x07001C, "KeyY",
+    KeyU, 0x070018, "KeyU",
+    KeyI, 0x07000C, "KeyI",
+    KeyO, 0x070012, "KeyO",
+    KeyP, 0x070013, "KeyP",
+    LeftBracket, 0x07002F, "BracketLeft",
+    RightBracket, 0x070030, "BracketRight",
+    KeyA, 0x070004, "KeyA",
+    KeyS, 0x070016, "KeyS",
+    KeyD, 0x070007, "KeyD",
+    KeyF, 0x070009, "KeyF",
+    KeyG, 0x07000A, "KeyG",
+    KeyH, 0x07000B, "KeyH",
+    KeyJ, 0x07000D, "KeyJ",
+    KeyK, 0x07000E, "KeyK",
+    KeyL, 0x07000F, "KeyL",
+    SemiColon, 0x070033, "Semicolon",
+    Quote, 0x070034, "Quote",
+    BackSlash, 0x070031, "Backslash",
+    IntlBackslash, 0x070064, "IntlBackslash",
+    KeyZ, 0x07001D, "KeyZ",
+    KeyX, 0x07001B, "KeyX",
+    KeyC, 0x070006, "KeyC",
+    KeyV, 0x070019, "KeyV",
+    KeyB, 0x070005, "KeyB",
+    KeyN, 0x070011, "KeyN",
+    KeyM, 0x070010, "KeyM",
+    Comma, 0x070036, "Comma",
+    Dot, 0x070037, "Period",
+    Slash, 0x070038, "Slash",
+    Insert, 0x070049, "Insert",
+    KpReturn, 0x070058, "NumpadEnter",
+    KpMinus, 0x070056, "NumpadSubtract",
+    KpPlus, 0x070057, "NumpadAdd",
+    KpMultiply, 0x070055, "NumpadMultiply",
+    KpDivide, 0x070054, "NumpadDivide",
+    Kp0, 0x070062, "Numpad0",
+    Kp1, 0x070059, "Numpad1",
+    Kp2, 0x07005A, "Numpad2",
+    Kp3, 0x07005B, "Numpad3",
+    Kp4, 0x07005C, "Numpad4",
+    Kp5, 0x07005D, "Numpad5",
+    Kp6, 0x07005E, "Numpad6",
+    Kp7, 0x07005F, "Numpad7",
+    Kp8, 0x070060, "Numpad8",
+    Kp9, 0x070061, "Numpad9",
+    KpDelete, 0x070063, "NumpadDecimal",
+    VolumeUp, 0x0C00E9, "AudioVolumeUp",
+    VolumeDown, 0x0C00EA, "AudioVolumeDown",
+    VolumeMute, 0x0C00E2, "AudioVolumeMute",
+    BrightnessUp, 0x0C006F, "BrightnessUp",
+    BrightnessDown, 0x0C0070, "BrightnessDown",
+    PreviousTrack, 0x0C00B6, "MediaTrackPrevious",
+    PlayPause, 0x0C00CD, "MediaPlayPause",
+    PlayCd, 0x0C00B0, "MediaPlay",
+    NextTrack, 0x0C00B5, "MediaTrackNext",
+    ContextMenu, 0x070065, "ContextMenu",
+    Power, 0x070066, "Power",
+    Sleep, 0x010082, "Sleep",
+    Wake, 0x010083, "WakeUp",
+    Calculator, 0x0C0192, "LaunchApp2",
+    Mail, 0x0C018A, "LaunchMail",
+    BrowserBack, 0x0C0224, "BrowserBack",
+    BrowserForward, 0x0C0225, "BrowserForward",
+    BrowserRefresh, 0x0C0227, "BrowserRefresh",
+    BrowserStop, 0x0C0226, "BrowserStop",
+    BrowserSearch, 0x0C0221, "BrowserSearch",
+    BrowserFavorites, 0x0C022A, "BrowserFavorites",
+    BrowserHome, 0x0C0223, "BrowserHome",
+    Stop, 0x0C00B7, "MediaStop",
+    Eject, 0x0C00B8, "Eject",
+    Ro, 0x070087, "IntlRo",
+    Yen, 0x070089, "IntlYen",
+    Henkan, 0x07008A, "Convert",
+    Muhenkan, 0x07008B, "NonConvert",
+    Hangul, 0x070090, "Lang1",
+    Hanja, 0x070091, "Lang2",
+    Help, 0x070075, "Help",
+    Undo, 0x07007A, "Undo",
+    Copy, 0x07007C, "Copy",
+    Paste, 0x07007D, "Paste",
+    Cut, 0x07007B, "Cut"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reversible_codes() {
+        for key in Key::VARIANTS.iter().copied() {
+            let code = key.to_w3c_code().unwrap();
+            assert_eq!(Key::from_w3c_code(code), Some(key));
+            match key.to_hid_usage() {
+                Some(usage) => assert_eq!(Key::from_hid_usage(usage), Some(key)),
+                None => assert_eq!(key, Key::Function),
+            }
+        }
+        assert_eq!(Key::KeyA.to_hid_usage(), Some(0x070004));
+        assert_eq!(Key::KpReturn.to_w3c_code(), Some("NumpadEnter"));
+        assert_eq!(Key::from_w3c_code("ShiftLeft"), Some(Key::ShiftLeft));
+        assert_eq!(Key::from_w3c_code("shiftleft"), None);
+        assert_eq!(Key::Unknown(4).to_hid_usage(), None);
+        assert_eq!(Key::from_hid_usage(0x070000), None);
+    }
+}
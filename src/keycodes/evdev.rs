@@ -0,0 +1,214 @@
+//! Linux evdev key codes, as used by libinput and uinput.
+
+use crate::rdev::Key;
+
+macro_rules! decl_keycodes {
+    ($($key:ident, $code:literal),*) => {
+        /// Evdev code of `key`, `None` if this platform has no such key.
+        #[allow(unreachable_patterns)]
+        pub const fn code_from_key(key: Key) -> Option<u32> {
+            match key {
+                $(
+                    Key::$key => Some($code),
+                )*
+                Key::Unknown(code) => Some(code),
+                _ => None,
+            }
+        }
+
+        /// Key of an evdev code, `Key::Unknown(code)` if there is none.
+        pub const fn key_from_code(code: u32) -> Key {
+            match code {
+                $(
+                    $code => Key::$key,
+                )*
+                _ => Key::Unknown(code)
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+decl_keycodes!(
+    Alt, 56,
+    AltGr, 100,
+    Backspace, 14,
+    CapsLock, 58,
+    ControlLeft, 29,
+    ControlRight, 97,
+    Delete, 111,
+    DownArrow, 108,
+    End, 107,
+    Escape, 1,
+    F1, 59,
+    F2, 60,
+    F3, 61,
+    F4, 62,
+    F5, 63,
+    F6, 64,
+    F7, 65,
+    F8, 66,
+    F9, 67,
+    F10, 68,
+    F11, 87,
+    F12, 88,
+    Home, 102,
+    LeftArrow, 105,
+    MetaLeft, 125,
+    PageDown, 109,
+    PageUp, 104,
+    Return, 28,
+    RightArrow, 106,
+    ShiftLeft, 42,
+    ShiftRight, 54,
+    Space, 57,
+    Tab, 15,
+    UpArrow, 103,
+    PrintScreen, 99,
+    ScrollLock, 70,
+    Pause, 119,
+    NumLock, 69,
+    BackQuote, 41,
+    Num1, 2,
+    Num2, 3,
+    Num3, 4,
+    Num4, 5,
+    Num5, 6,
+    Num6, 7,
+    Num7, 8,
+    Num8, 9,
+    Num9, 10,
+    Num0, 11,
+    Minus, 12,
+    Equal, 13,
+    KeyQ, 16,
+    KeyW, 17,
+    KeyE, 18,
+    KeyR, 19,
+    KeyT, 20,
+    KeyY, 21,
+    KeyU, 22,
+    KeyI, 23,
+    KeyO, 24,
+    KeyP, 25,
+    LeftBracket, 26,
+    RightBracket, 27,
+    KeyA, 30,
+    KeyS, 31,
+    KeyD, 32,
+    KeyF, 33,
+    KeyG, 34,
+    KeyH, 35,
+    KeyJ, 36,
+    KeyK, 37,
+    KeyL, 38,
+    SemiColon, 39,
+    Quote, 40,
+    BackSlash, 43,
+    IntlBackslash, 86,
+    KeyZ, 44,
+    KeyX, 45,
+    KeyC, 46,
+    KeyV, 47,
+    KeyB, 48,
+    KeyN, 49,
+    KeyM, 50,
+    Comma, 51,
+    Dot, 52,
+    Slash, 53,
+    Insert, 110,
+    KpReturn, 96,
+    KpMinus, 74,
+    KpPlus, 78,
+    KpMultiply, 55,
+    KpDivide, 98,
+    Kp0, 82,
+    Kp1, 79,
+    Kp2, 80,
+    Kp3, 81,
+    Kp4, 75,
+    Kp5, 76,
+    Kp6, 77,
+    Kp7, 71,
+    Kp8, 72,
+    Kp9, 73,
+    KpDelete, 83,
+    VolumeMute, 113,
+    VolumeDown, 114,
+    VolumeUp, 115,
+    NextTrack, 163,
+    PlayPause, 164,
+    PreviousTrack, 165,
+    PlayCd, 200,
+    MetaRight, 126,
+    BrightnessDown, 224,
+    BrightnessUp, 225,
+    F13, 183,
+    F14, 184,
+    F15, 185,
+    F16, 186,
+    F17, 187,
+    F18, 188,
+    F19, 189,
+    F20, 190,
+    F21, 191,
+    F22, 192,
+    F23, 193,
+    F24, 194,
+    ContextMenu, 127,
+    Power, 116,
+    Sleep, 142,
+    Wake, 143,
+    Calculator, 140,
+    Mail, 155,
+    BrowserBack, 158,
+    BrowserForward, 159,
+    BrowserRefresh, 173,
+    BrowserStop, 128,
+    BrowserSearch, 217,
+    BrowserFavorites, 156,
+    BrowserHome, 172,
+    Stop, 166,
+    Eject, 161,
+    Ro, 89,
+    Yen, 124,
+    Henkan, 92,
+    Muhenkan, 94,
+    Hangul, 122,
+    Hanja, 123,
+    Help, 138,
+    Undo, 131,
+    Copy, 133,
+    Paste, 135,
+    Cut, 137,
+    Function, 464
+);
+
+#[cfg(test)]
+mod test {
+    use super::{code_from_key, key_from_code};
+    use crate::rdev::Key;
+
+    #[test]
+    fn test_linux_input_codes() {
+        // KEY_RIGHTALT, KEY_INSERT and KEY_DELETE in linux/input-event-codes.h,
+        // these used to be off.
+        assert_eq!(code_from_key(Key::AltGr), Some(100));
+        assert_eq!(code_from_key(Key::Insert), Some(110));
+        assert_eq!(code_from_key(Key::Delete), Some(111));
+        assert_eq!(key_from_code(100), Key::AltGr);
+        assert_eq!(key_from_code(110), Key::Insert);
+        assert_eq!(key_from_code(111), Key::Delete);
+    }
+
+    #[test]
+    fn test_reversible() {
+        for code in 0..65636 {
+            let key = key_from_code(code);
+            match code_from_key(key) {
+                Some(code2) => assert_eq!(code, code2),
+                None => panic!("Could not convert back code: {:?}", code),
+            }
+        }
+    }
+}
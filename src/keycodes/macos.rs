@@ -1,90 +1,128 @@
-use objc2_core_graphics::CGKeyCode;
+//! macOS virtual key codes (`CGKeyCode`) and special key codes (`NX_KEYTYPE_*`).
 
 use crate::rdev::Key;
-use std::convert::TryInto;
 
 /// Option
-const ALT: CGKeyCode = 58;
+const ALT: u16 = 58;
 /// Option_Right
-const ALT_GR: CGKeyCode = 61;
-const BACKSPACE: CGKeyCode = 51;
-const CAPS_LOCK: CGKeyCode = 57;
-const CONTROL_LEFT: CGKeyCode = 59;
-const CONTROL_RIGHT: CGKeyCode = 62;
-const DOWN_ARROW: CGKeyCode = 125;
-const ESCAPE: CGKeyCode = 53;
-const F1: CGKeyCode = 122;
-const F10: CGKeyCode = 109;
-const F11: CGKeyCode = 103;
-const F12: CGKeyCode = 111;
-const F2: CGKeyCode = 120;
-const F3: CGKeyCode = 99;
-const F4: CGKeyCode = 118;
-const F5: CGKeyCode = 96;
-const F6: CGKeyCode = 97;
-const F7: CGKeyCode = 98;
-const F8: CGKeyCode = 100;
-const F9: CGKeyCode = 101;
-const FUNCTION: CGKeyCode = 63;
-const LEFT_ARROW: CGKeyCode = 123;
-const META_LEFT: CGKeyCode = 55;
-const META_RIGHT: CGKeyCode = 54;
-const RETURN: CGKeyCode = 36;
-const RIGHT_ARROW: CGKeyCode = 124;
-const SHIFT_LEFT: CGKeyCode = 56;
-const SHIFT_RIGHT: CGKeyCode = 60;
-const SPACE: CGKeyCode = 49;
-const TAB: CGKeyCode = 48;
-const UP_ARROW: CGKeyCode = 126;
-const BACK_QUOTE: CGKeyCode = 50;
-const NUM1: CGKeyCode = 18;
-const NUM2: CGKeyCode = 19;
-const NUM3: CGKeyCode = 20;
-const NUM4: CGKeyCode = 21;
-const NUM5: CGKeyCode = 23;
-const NUM6: CGKeyCode = 22;
-const NUM7: CGKeyCode = 26;
-const NUM8: CGKeyCode = 28;
-const NUM9: CGKeyCode = 25;
-const NUM0: CGKeyCode = 29;
-const MINUS: CGKeyCode = 27;
-const EQUAL: CGKeyCode = 24;
-const KEY_Q: CGKeyCode = 12;
-const KEY_W: CGKeyCode = 13;
-const KEY_E: CGKeyCode = 14;
-const KEY_R: CGKeyCode = 15;
-const KEY_T: CGKeyCode = 17;
-const KEY_Y: CGKeyCode = 16;
-const KEY_U: CGKeyCode = 32;
-const KEY_I: CGKeyCode = 34;
-const KEY_O: CGKeyCode = 31;
-const KEY_P: CGKeyCode = 35;
-const LEFT_BRACKET: CGKeyCode = 33;
-const RIGHT_BRACKET: CGKeyCode = 30;
-const KEY_A: CGKeyCode = 0;
-const KEY_S: CGKeyCode = 1;
-const KEY_D: CGKeyCode = 2;
-const KEY_F: CGKeyCode = 3;
-const KEY_G: CGKeyCode = 5;
-const KEY_H: CGKeyCode = 4;
-const KEY_J: CGKeyCode = 38;
-const KEY_K: CGKeyCode = 40;
-const KEY_L: CGKeyCode = 37;
-const SEMI_COLON: CGKeyCode = 41;
-const QUOTE: CGKeyCode = 39;
-const BACK_SLASH: CGKeyCode = 42;
-const KEY_Z: CGKeyCode = 6;
-const KEY_X: CGKeyCode = 7;
-const KEY_C: CGKeyCode = 8;
-const KEY_V: CGKeyCode = 9;
-const KEY_B: CGKeyCode = 11;
-const KEY_N: CGKeyCode = 45;
-const KEY_M: CGKeyCode = 46;
-const COMMA: CGKeyCode = 43;
-const DOT: CGKeyCode = 47;
-const SLASH: CGKeyCode = 44;
+const ALT_GR: u16 = 61;
+const BACKSPACE: u16 = 51;
+const CAPS_LOCK: u16 = 57;
+const CONTROL_LEFT: u16 = 59;
+const CONTROL_RIGHT: u16 = 62;
+const DOWN_ARROW: u16 = 125;
+const ESCAPE: u16 = 53;
+const F1: u16 = 122;
+const F10: u16 = 109;
+const F11: u16 = 103;
+const F12: u16 = 111;
+const F2: u16 = 120;
+const F3: u16 = 99;
+const F4: u16 = 118;
+const F5: u16 = 96;
+const F6: u16 = 97;
+const F7: u16 = 98;
+const F8: u16 = 100;
+const F9: u16 = 101;
+const FUNCTION: u16 = 63;
+const LEFT_ARROW: u16 = 123;
+const META_LEFT: u16 = 55;
+const META_RIGHT: u16 = 54;
+const RETURN: u16 = 36;
+const RIGHT_ARROW: u16 = 124;
+const SHIFT_LEFT: u16 = 56;
+const SHIFT_RIGHT: u16 = 60;
+const SPACE: u16 = 49;
+const TAB: u16 = 48;
+const UP_ARROW: u16 = 126;
+const BACK_QUOTE: u16 = 50;
+const NUM1: u16 = 18;
+const NUM2: u16 = 19;
+const NUM3: u16 = 20;
+const NUM4: u16 = 21;
+const NUM5: u16 = 23;
+const NUM6: u16 = 22;
+const NUM7: u16 = 26;
+const NUM8: u16 = 28;
+const NUM9: u16 = 25;
+const NUM0: u16 = 29;
+const MINUS: u16 = 27;
+const EQUAL: u16 = 24;
+const KEY_Q: u16 = 12;
+const KEY_W: u16 = 13;
+const KEY_E: u16 = 14;
+const KEY_R: u16 = 15;
+const KEY_T: u16 = 17;
+const KEY_Y: u16 = 16;
+const KEY_U: u16 = 32;
+const KEY_I: u16 = 34;
+const KEY_O: u16 = 31;
+const KEY_P: u16 = 35;
+const LEFT_BRACKET: u16 = 33;
+const RIGHT_BRACKET: u16 = 30;
+const KEY_A: u16 = 0;
+const KEY_S: u16 = 1;
+const KEY_D: u16 = 2;
+const KEY_F: u16 = 3;
+const KEY_G: u16 = 5;
+const KEY_H: u16 = 4;
+const KEY_J: u16 = 38;
+const KEY_K: u16 = 40;
+const KEY_L: u16 = 37;
+const SEMI_COLON: u16 = 41;
+const QUOTE: u16 = 39;
+const BACK_SLASH: u16 = 42;
+const KEY_Z: u16 = 6;
+const KEY_X: u16 = 7;
+const KEY_C: u16 = 8;
+const KEY_V: u16 = 9;
+const KEY_B: u16 = 11;
+const KEY_N: u16 = 45;
+const KEY_M: u16 = 46;
+const COMMA: u16 = 43;
+const DOT: u16 = 47;
+const SLASH: u16 = 44;
+const DELETE: u16 = 117;
+const END: u16 = 119;
+const HOME: u16 = 115;
+const PAGE_DOWN: u16 = 121;
+const PAGE_UP: u16 = 116;
+const F13: u16 = 105;
+const F14: u16 = 107;
+const F15: u16 = 113;
+const F16: u16 = 106;
+const F17: u16 = 64;
+const F18: u16 = 79;
+const F19: u16 = 80;
+const F20: u16 = 90;
+/// Section sign key of ISO keyboards
+const INTL_BACKSLASH: u16 = 10;
+const KP_RETURN: u16 = 76;
+const KP_MINUS: u16 = 78;
+const KP_PLUS: u16 = 69;
+const KP_MULTIPLY: u16 = 67;
+const KP_DIVIDE: u16 = 75;
+const KP0: u16 = 82;
+const KP1: u16 = 83;
+const KP2: u16 = 84;
+const KP3: u16 = 85;
+const KP4: u16 = 86;
+const KP5: u16 = 87;
+const KP6: u16 = 88;
+const KP7: u16 = 89;
+const KP8: u16 = 91;
+const KP9: u16 = 92;
+/// Keypad decimal
+const KP_DELETE: u16 = 65;
+/// Insert on PC keyboards
+const HELP: u16 = 114;
+const CONTEXT_MENU: u16 = 110;
+const YEN: u16 = 93;
+/// JIS underscore
+const RO: u16 = 94;
 
-pub fn code_from_key(key: Key) -> Option<CGKeyCode> {
+/// Virtual key code of `key`, `None` if this platform has no such key.
+pub const fn code_from_key(key: Key) -> Option<u16> {
     match key {
         Key::Alt => Some(ALT),
         Key::AltGr => Some(ALT_GR),
@@ -164,18 +202,54 @@ pub fn code_from_key(key: Key) -> Option<CGKeyCode> {
         Key::Dot => Some(DOT),
         Key::Slash => Some(SLASH),
         Key::Function => Some(FUNCTION),
-        Key::Unknown(code) => code.try_into().ok(),
+        Key::Delete => Some(DELETE),
+        Key::End => Some(END),
+        Key::Home => Some(HOME),
+        Key::PageDown => Some(PAGE_DOWN),
+        Key::PageUp => Some(PAGE_UP),
+        Key::F13 => Some(F13),
+        Key::F14 => Some(F14),
+        Key::F15 => Some(F15),
+        Key::F16 => Some(F16),
+        Key::F17 => Some(F17),
+        Key::F18 => Some(F18),
+        Key::F19 => Some(F19),
+        Key::F20 => Some(F20),
+        Key::IntlBackslash => Some(INTL_BACKSLASH),
+        Key::KpReturn => Some(KP_RETURN),
+        Key::KpMinus => Some(KP_MINUS),
+        Key::KpPlus => Some(KP_PLUS),
+        Key::KpMultiply => Some(KP_MULTIPLY),
+        Key::KpDivide => Some(KP_DIVIDE),
+        Key::Kp0 => Some(KP0),
+        Key::Kp1 => Some(KP1),
+        Key::Kp2 => Some(KP2),
+        Key::Kp3 => Some(KP3),
+        Key::Kp4 => Some(KP4),
+        Key::Kp5 => Some(KP5),
+        Key::Kp6 => Some(KP6),
+        Key::Kp7 => Some(KP7),
+        Key::Kp8 => Some(KP8),
+        Key::Kp9 => Some(KP9),
+        Key::KpDelete => Some(KP_DELETE),
+        Key::Help => Some(HELP),
+        Key::ContextMenu => Some(CONTEXT_MENU),
+        Key::Yen => Some(YEN),
+        Key::Ro => Some(RO),
+        Key::Unknown(code) if code <= u16::MAX as u32 => Some(code as u16),
         _ => None,
     }
 }
 
-pub fn key_from_code(code: CGKeyCode) -> Key {
+/// Key of a virtual key code, `Key::Unknown(code)` if there is none.
+pub const fn key_from_code(code: u16) -> Key {
     match code {
         ALT => Key::Alt,
         ALT_GR => Key::AltGr,
         BACKSPACE => Key::Backspace,
         CAPS_LOCK => Key::CapsLock,
         CONTROL_LEFT => Key::ControlLeft,
+        CONTROL_RIGHT => Key::ControlRight,
         DOWN_ARROW => Key::DownArrow,
         ESCAPE => Key::Escape,
         F1 => Key::F1,
@@ -248,7 +322,41 @@ pub fn key_from_code(code: CGKeyCode) -> Key {
         DOT => Key::Dot,
         SLASH => Key::Slash,
         FUNCTION => Key::Function,
-        code => Key::Unknown(code.into()),
+        DELETE => Key::Delete,
+        END => Key::End,
+        HOME => Key::Home,
+        PAGE_DOWN => Key::PageDown,
+        PAGE_UP => Key::PageUp,
+        F13 => Key::F13,
+        F14 => Key::F14,
+        F15 => Key::F15,
+        F16 => Key::F16,
+        F17 => Key::F17,
+        F18 => Key::F18,
+        F19 => Key::F19,
+        F20 => Key::F20,
+        INTL_BACKSLASH => Key::IntlBackslash,
+        KP_RETURN => Key::KpReturn,
+        KP_MINUS => Key::KpMinus,
+        KP_PLUS => Key::KpPlus,
+        KP_MULTIPLY => Key::KpMultiply,
+        KP_DIVIDE => Key::KpDivide,
+        KP0 => Key::Kp0,
+        KP1 => Key::Kp1,
+        KP2 => Key::Kp2,
+        KP3 => Key::Kp3,
+        KP4 => Key::Kp4,
+        KP5 => Key::Kp5,
+        KP6 => Key::Kp6,
+        KP7 => Key::Kp7,
+        KP8 => Key::Kp8,
+        KP9 => Key::Kp9,
+        KP_DELETE => Key::KpDelete,
+        HELP => Key::Help,
+        CONTEXT_MENU => Key::ContextMenu,
+        YEN => Key::Yen,
+        RO => Key::Ro,
+        code => Key::Unknown(code as u32),
     }
 }
 
@@ -269,9 +377,7 @@ const NX_KEYTYPE_SOUND_DOWN: u32 = 1;
 const NX_KEYTYPE_BRIGHTNESS_UP: u32 = 2;
 const NX_KEYTYPE_BRIGHTNESS_DOWN: u32 = 3;
 const NX_KEYTYPE_CAPS_LOCK: u32 = 4;
-#[allow(unused)]
 const NX_KEYTYPE_HELP: u32 = 5;
-#[allow(unused)]
 const NX_POWER_KEY: u32 = 6;
 const NX_KEYTYPE_MUTE: u32 = 7;
 const NX_UP_ARROW_KEY: u32 = 8;
@@ -284,7 +390,6 @@ const NX_KEYTYPE_CONTRAST_UP: u32 = 11;
 const NX_KEYTYPE_CONTRAST_DOWN: u32 = 12;
 #[allow(unused)]
 const NX_KEYTYPE_LAUNCH_PANEL: u32 = 13;
-#[allow(unused)]
 const NX_KEYTYPE_EJECT: u32 = 14;
 #[allow(unused)]
 const NX_KEYTYPE_VIDMIRROR: u32 = 15;
@@ -310,7 +415,8 @@ const NX_NUMSPECIALKEYS: u32 = 24; /* Maximum number of special keys */
 const NX_NUM_SCANNED_SPECIALKEYS: u32 = 24; /* First 24 special keys are */
 /* actively scanned in kernel */
 
-pub fn key_from_special_key(code: u32) -> Option<Key> {
+/// Key of an `NX_KEYTYPE_*` special key code, from system defined events.
+pub const fn key_from_special_key(code: u32) -> Option<Key> {
     match code {
         NX_KEYTYPE_SOUND_UP => Some(Key::VolumeUp),
         NX_KEYTYPE_SOUND_DOWN => Some(Key::VolumeDown),
@@ -324,13 +430,40 @@ pub fn key_from_special_key(code: u32) -> Option<Key> {
         NX_UP_ARROW_KEY => Some(Key::UpArrow),
         NX_DOWN_ARROW_KEY => Some(Key::DownArrow),
         NX_KEYTYPE_NUM_LOCK => Some(Key::NumLock),
+        NX_KEYTYPE_HELP => Some(Key::Help),
+        NX_POWER_KEY => Some(Key::Power),
+        NX_KEYTYPE_EJECT => Some(Key::Eject),
+        _ => None,
+    }
+}
+
+/// `NX_KEYTYPE_*` special key code of `key`, the inverse of `key_from_special_key`.
+/// Caps lock, the arrows and help also have a virtual key code, which is what
+/// keyboards usually send for them.
+pub const fn special_key_from_key(key: Key) -> Option<u32> {
+    match key {
+        Key::VolumeUp => Some(NX_KEYTYPE_SOUND_UP),
+        Key::VolumeDown => Some(NX_KEYTYPE_SOUND_DOWN),
+        Key::VolumeMute => Some(NX_KEYTYPE_MUTE),
+        Key::BrightnessUp => Some(NX_KEYTYPE_BRIGHTNESS_UP),
+        Key::BrightnessDown => Some(NX_KEYTYPE_BRIGHTNESS_DOWN),
+        Key::PlayPause => Some(NX_KEYTYPE_PLAY),
+        Key::NextTrack => Some(NX_KEYTYPE_NEXT),
+        Key::PreviousTrack => Some(NX_KEYTYPE_PREVIOUS),
+        Key::CapsLock => Some(NX_KEYTYPE_CAPS_LOCK),
+        Key::UpArrow => Some(NX_UP_ARROW_KEY),
+        Key::DownArrow => Some(NX_DOWN_ARROW_KEY),
+        Key::NumLock => Some(NX_KEYTYPE_NUM_LOCK),
+        Key::Help => Some(NX_KEYTYPE_HELP),
+        Key::Power => Some(NX_POWER_KEY),
+        Key::Eject => Some(NX_KEYTYPE_EJECT),
         _ => None,
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{code_from_key, key_from_code};
+    use super::{code_from_key, key_from_code, key_from_special_key, special_key_from_key};
     #[test]
     fn test_reversible() {
         for code in 0..=65535 {
@@ -341,4 +474,13 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_reversible_special_keys() {
+        for code in 0..64 {
+            if let Some(key) = key_from_special_key(code) {
+                assert_eq!(special_key_from_key(key), Some(code));
+            }
+        }
+    }
 }
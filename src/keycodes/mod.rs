@@ -0,0 +1,18 @@
+//! Native keycode tables of every backend, compiled on every target so codes coming
+//! from another OS can be translated, e.g. on a server receiving keys from remote
+//! clients. `Key::Unknown(code)` carries codes without a `Key` and converts back
+//! to the same code.
+//!
+//! ```
+//! use rdev::Key;
+//! use rdev::keycodes::{evdev, macos};
+//!
+//! let key = macos::key_from_code(0);
+//! assert_eq!(key, Key::KeyA);
+//! assert_eq!(evdev::code_from_key(key), Some(30));
+//! ```
+
+pub mod evdev;
+pub mod macos;
+pub mod windows_vk;
+pub mod x11;
@@ -1,27 +1,27 @@
+//! Windows virtual-key codes.
+
 use crate::rdev::Key;
-use std::convert::TryInto;
-use winapi::shared::minwindef::WORD;
 
 macro_rules! decl_keycodes {
     ($($key:ident, $code:literal),*) => {
-        //TODO: make const when rust lang issue #49146 is fixed
-        pub fn code_from_key(key: Key) -> Option<WORD> {
+        /// Virtual-key code of `key`, `None` if this platform has no such key.
+        pub const fn code_from_key(key: Key) -> Option<u16> {
             match key {
                 $(
                     Key::$key => Some($code),
                 )*
-                Key::Unknown(code) => Some(code.try_into().ok()?),
+                Key::Unknown(code) if code <= u16::MAX as u32 => Some(code as u16),
                 _ => None,
             }
         }
 
-        //TODO: make const when rust lang issue #49146 is fixed
-        pub fn key_from_code(code: WORD) -> Key {
+        /// Key of a virtual-key code, `Key::Unknown(code)` if there is none.
+        pub const fn key_from_code(code: u16) -> Key {
             match code {
                 $(
                     $code => Key::$key,
                 )*
-                _ => Key::Unknown(code.into())
+                _ => Key::Unknown(code as u32)
             }
         }
     };
@@ -151,7 +151,24 @@ decl_keycodes! {
     VolumeUp, 175,
     NextTrack, 176,
     PreviousTrack, 177,
-    PlayPause, 179
+    PlayPause, 179,
+    ContextMenu, 93,
+    Sleep, 95,
+    Calculator, 183,
+    Mail, 180,
+    BrowserBack, 166,
+    BrowserForward, 167,
+    BrowserRefresh, 168,
+    BrowserStop, 169,
+    BrowserSearch, 170,
+    BrowserFavorites, 171,
+    BrowserHome, 172,
+    Stop, 178,
+    Henkan, 28,
+    Muhenkan, 29,
+    Hangul, 21,
+    Hanja, 25,
+    Help, 47
 }
 
 #[cfg(test)]
@@ -1,10 +1,11 @@
+//! X11 keycodes, evdev codes offset by 8.
+
 use crate::rdev::Key;
-use std::os::raw::c_uint;
 
 macro_rules! decl_keycodes {
     ($($key:ident, $code:literal),*) => {
-        //TODO: make const when rust lang issue #49146 is fixed
-        pub fn code_from_key(key: Key) -> Option<c_uint> {
+        /// X11 keycode of `key`, `None` if this platform has no such key.
+        pub const fn code_from_key(key: Key) -> Option<u32> {
             match key {
                 $(
                     Key::$key => Some($code),
@@ -14,8 +15,8 @@ macro_rules! decl_keycodes {
             }
         }
 
-        //TODO: make const when rust lang issue #49146 is fixed
-        pub fn key_from_code(code: c_uint) -> Key {
+        /// Key of an X11 keycode, `Key::Unknown(code)` if there is none.
+        pub const fn key_from_code(code: u32) -> Key {
             match code {
                 $(
                     $code => Key::$key,
@@ -130,7 +131,55 @@ decl_keycodes!(
     Kp7, 79,
     Kp8, 80,
     Kp9, 81,
-    KpDelete, 91
+    KpDelete, 91,
+    MetaRight, 134,
+    VolumeMute, 121,
+    VolumeDown, 122,
+    VolumeUp, 123,
+    NextTrack, 171,
+    PlayPause, 172,
+    PreviousTrack, 173,
+    PlayCd, 208,
+    BrightnessDown, 232,
+    BrightnessUp, 233,
+    F13, 191,
+    F14, 192,
+    F15, 193,
+    F16, 194,
+    F17, 195,
+    F18, 196,
+    F19, 197,
+    F20, 198,
+    F21, 199,
+    F22, 200,
+    F23, 201,
+    F24, 202,
+    ContextMenu, 135,
+    Power, 124,
+    Sleep, 150,
+    Wake, 151,
+    Calculator, 148,
+    Mail, 163,
+    BrowserBack, 166,
+    BrowserForward, 167,
+    BrowserRefresh, 181,
+    BrowserStop, 136,
+    BrowserSearch, 225,
+    BrowserFavorites, 164,
+    BrowserHome, 180,
+    Stop, 174,
+    Eject, 169,
+    Ro, 97,
+    Yen, 132,
+    Henkan, 100,
+    Muhenkan, 102,
+    Hangul, 130,
+    Hanja, 131,
+    Help, 146,
+    Undo, 139,
+    Copy, 141,
+    Paste, 143,
+    Cut, 145
 );
 
 #[cfg(test)]
@@ -39,18 +39,72 @@
 //! ## Linux
 //! The `listen` function uses X11 APIs, and so will not work in Wayland or in the linux kernel virtual console
 //!
+//! With the `wayland` feature, `listen` reads libinput instead. Wayland has no global cursor
+//! position, so `MouseMove` is integrated from the relative motion and clamped to the outputs.
+//! The layout and the starting position are asked to Sway or Hyprland, use `set_output_layout`
+//! and `set_pointer_position` to provide them otherwise.
+//!
+//! ## Stopping the loop
+//!
+//! `listen` never returns. Use `listen_until` with a `ListenHandle` (or `grab_until`
+//! with a `GrabHandle`) if you need to stop capturing from another thread.
+//!
+//! ```no_run
+//! use rdev::{listen_until, ListenHandle};
+//!
+//! let handle = ListenHandle::new();
+//! let stopper = handle.clone();
+//! std::thread::spawn(move || {
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
+//!     stopper.stop();
+//! });
+//! listen_until(|event| println!("{:?}", event), &handle).unwrap();
+//! ```
+//!
+//! ## Receiving events through a channel
+//!
+//! `listen_channel` runs the listener on a background thread and hands back
+//! an iterator over the events. Dropping the receiver stops the listener.
+//!
+//! ```no_run
+//! use rdev::listen_channel;
+//!
+//! for event in listen_channel() {
+//!     println!("Received {:?}", event);
+//! }
+//! ```
+//!
+//! ## Async stream
+//!
+//! With the `async` feature, `listen_stream` returns a `futures::Stream` of
+//! events instead. On Linux the stream is polled on the thread creating it,
+//! it is not `Send`.
+//!
+//! ```no_run
+//! # #[cfg(feature = "async")]
+//! # async fn run() {
+//! use futures::StreamExt;
+//! use rdev::listen_stream;
+//!
+//! let mut events = listen_stream();
+//! while let Some(event) = events.next().await {
+//!     println!("Received {:?}", event);
+//! }
+//! # }
+//! ```
+//!
 //! # Sending some events
 //!
 //! ```no_run
-//! use rdev::{simulate, Button, EventType, Key, SimulateError};
+//! use rdev::{simulate, Button, EventType, Key};
 //! use std::{thread, time};
 //!
 //! fn send(event_type: &EventType) {
 //!     let delay = time::Duration::from_millis(20);
 //!     match simulate(event_type) {
 //!         Ok(()) => (),
-//!         Err(SimulateError) => {
-//!             println!("We could not send {:?}", event_type);
+//!         Err(error) => {
+//!             println!("We could not send {:?}: {}", event_type, error);
 //!         }
 //!     }
 //!     // Let the OS catchup (at least MacOS)
@@ -69,6 +123,16 @@
 //!     delta_y: 1,
 //! });
 //! ```
+//!
+//! Text is typed with [`simulate_text`], which finds the keys (with Shift, AltGr
+//! or dead keys) typing each character on the active layout. The events are sent
+//! 20ms apart for MacOS, [`simulate_text_with_delay`] changes the delay.
+//!
+//! ```no_run
+//! if let Err(error) = rdev::simulate_text("Voilà, 10 €") {
+//!     println!("We could not type the text: {}", error);
+//! }
+//! ```
 //! # Main structs
 //! ## Event
 //!
@@ -80,15 +144,39 @@
 //! what key was interpreted by the OS at that time, it will respect the layout.
 //!
 //! ```no_run
-//! # use crate::rdev::EventType;
-//! # use std::time::SystemTime;
+//! # use crate::rdev::{DeviceInfo, EventSource, EventType, Modifiers, RawCode};
+//! # use std::sync::Arc;
+//! # use std::time::{Duration, SystemTime};
 //! /// When events arrive from the system we can add some information
 //! /// time is when the event was received.
 //! #[derive(Debug)]
 //! pub struct Event {
 //!     pub time: SystemTime,
+//!     pub timestamp: Option<Duration>,
 //!     pub name: Option<String>,
 //!     pub event_type: EventType,
+//!     pub device: Option<Arc<DeviceInfo>>,
+//!     pub source: EventSource,
+//!     pub modifiers: Modifiers,
+//!     pub raw_code: Option<RawCode>,
+//! }
+//! ```
+//!
+//! `Event::modifiers` is the state of the modifier keys and locks once the event happened,
+//! enough to recognize shortcuts without tracking the modifiers yourself.
+//!
+//! ```no_run
+//! use rdev::{listen, Event, EventType, Key, Modifiers};
+//!
+//! fn callback(event: Event) {
+//!     if event.event_type == EventType::KeyPress(Key::KeyS)
+//!         && event.modifiers.contains(Modifiers::CTRL)
+//!     {
+//!         println!("Save !");
+//!     }
+//! }
+//! if let Err(error) = listen(callback) {
+//!     println!("Error: {:?}", error)
 //! }
 //! ```
 //!
@@ -108,7 +196,7 @@
 //! still reject it).
 //!
 //! ```no_run
-//! # use crate::rdev::{Key, Button};
+//! # use crate::rdev::{Key, Button, GesturePhase, ScrollSource, SwitchKind, TabletAxes, ToolType};
 //! /// In order to manage different OS, the current EventType choices is a mix&match
 //! /// to account for all possible events.
 //! #[derive(Debug)]
@@ -117,6 +205,8 @@
 //!     /// To the actual letter a user would use, that requires some layout logic to be added.
 //!     KeyPress(Key),
 //!     KeyRelease(Key),
+//!     /// Autorepeat of a held key, not a new press.
+//!     KeyRepeat(Key),
 //!     /// Some mouse will have more than 3 buttons, these are not defined, and different OS will
 //!     /// give different Unknown code.
 //!     ButtonPress(Button),
@@ -126,17 +216,46 @@
 //!         x: f64,
 //!         y: f64,
 //!     },
+//!     /// Relative motion, e.g. raw mouse deltas
+//!     MouseMoveRelative {
+//!         dx: f64,
+//!         dy: f64,
+//!     },
 //!     /// Note: On Linux, there is no actual delta the actual values are ignored for delta_x
 //!     /// and we only look at the sign of delta_y to simulate wheelup or wheeldown.
 //!     Wheel {
 //!         delta_x: i64,
 //!         delta_y: i64,
 //!     },
+//!     /// High resolution scrolling, in (fractional) wheel notches.
+//!     Scroll {
+//!         delta_x: f64,
+//!         delta_y: f64,
+//!         source: ScrollSource,
+//!     },
+//!     /// Touchpad gestures, Wayland only.
+//!     Swipe { phase: GesturePhase, fingers: u32, dx: f64, dy: f64 },
+//!     Pinch { phase: GesturePhase, fingers: u32, dx: f64, dy: f64, scale: f64, rotation: f64 },
+//!     Hold { phase: GesturePhase, fingers: u32 },
+//!     /// Touchscreens, Linux only.
+//!     TouchDown { id: u32, x: f64, y: f64 },
+//!     TouchMove { id: u32, x: f64, y: f64 },
+//!     TouchUp { id: u32 },
+//!     TouchCancel { id: u32 },
+//!     TouchFrame,
+//!     /// Drawing tablets, Wayland only.
+//!     TabletProximity { tool: ToolType, in_proximity: bool, axes: TabletAxes },
+//!     TabletTip { tool: ToolType, down: bool, axes: TabletAxes },
+//!     TabletAxis { tool: ToolType, axes: TabletAxes },
+//!     TabletButton { tool: ToolType, button: u32, pressed: bool },
+//!     TabletPadButton { button: u32, pressed: bool },
+//!     /// Lid and tablet mode switches, Linux only.
+//!     Switch { kind: SwitchKind, on: bool },
 //! }
 //! ```
 //!
 //!
-//! # Getting the main screen size
+//! # Getting the screen size
 //!
 //! ```no_run
 //! use rdev::{display_size};
@@ -165,6 +284,35 @@
 //! // string == Some("s")
 //! ```
 //!
+//! # Names of keys and events
+//!
+//! `Key`, `Button` and `EventType` implement `Display` and `FromStr` for config files
+//! and command lines. The canonical names are the variant names (`ControlLeft`,
+//! `KpReturn`, `Unknown(42)`), events are written with their fields in order.
+//! Parsing ignores case, and keys also accept aliases like `ctrl`, `cmd`, `win` or
+//! `esc`. Displaying then parsing always gives back the same value.
+//!
+//! ```
+//! use rdev::{EventType, Key};
+//!
+//! let event_type: EventType = "KeyPress(ctrl)".parse().unwrap();
+//! assert_eq!(event_type, EventType::KeyPress(Key::ControlLeft));
+//! assert_eq!(event_type.to_string(), "KeyPress(ControlLeft)");
+//! ```
+//!
+//! Keys also map to USB HID usages and W3C `KeyboardEvent.code` strings on every
+//! platform, to exchange events with browsers or keyboard firmware.
+//!
+//! ```
+//! use rdev::Key;
+//!
+//! assert_eq!(Key::from_w3c_code("NumpadEnter"), Some(Key::KpReturn));
+//! assert_eq!(Key::KeyA.to_hid_usage(), Some(0x070004));
+//! ```
+//!
+//! The native keycodes of each backend are in the [`keycodes`] module, on every
+//! platform, to translate codes received from another OS.
+//!
 //! # Grabbing global events. (Requires `unstable_grab` feature)
 //!
 //! Installing this library with the `unstable_grab` feature adds the `grab` function
@@ -172,7 +320,10 @@
 //! by supplying this function with a callback, you can intercept
 //! all keyboard and mouse events before they are delivered to applications / window managers.
 //! In the callback, returning None ignores the event and returning the event let's it pass.
-//! There is no modification of the event possible here (yet).
+//! On Linux, returning a different event replaces the original one, which makes key remapping
+//! possible. On Windows and MacOS the returned event is only used to let the original one pass.
+//! Also on Linux, `grab_multi` lets the callback replace an event by any number of events
+//! (e.g. a key by a chord).
 //!
 //! Note: the use of the word `unstable` here refers specifically to the fact that the `grab` API is unstable and subject to change
 //!
@@ -217,11 +368,22 @@
 //!
 //! Event data returned by the `listen` and `grab` functions can be serialized and de-serialized with
 //! Serde if you install this library with the `serialize` feature.
+mod channel;
+mod codes;
+pub mod keycodes;
+mod names;
 mod rdev;
+#[cfg(all(feature = "async", any(target_os = "macos", target_os = "windows")))]
+mod stream;
+mod text;
+pub use crate::channel::{EventReceiver, listen_channel};
 pub use crate::rdev::{
-    Button, DisplayError, Event, EventType, GrabCallback, GrabError, Key, KeyboardState,
-    ListenError, SimulateError,
+    Button, DeviceInfo, DisplayError, Event, EventSource, EventType, GesturePhase, GrabCallback,
+    GrabError, GrabHandle, Key, KeyboardState, ListenError, ListenHandle, Modifiers,
+    ParseNameError, RawCode, ScrollSource, SimulateError, SwitchKind, TabletAxes, ToolType,
 };
+#[cfg(all(feature = "async", any(target_os = "macos", target_os = "windows")))]
+pub use crate::stream::{EventStream, listen_stream};
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -234,6 +396,15 @@ use crate::macos::{display_size as _display_size, listen as _listen, simulate as
 mod linux;
 #[cfg(all(target_family = "unix", not(target_os = "macos")))]
 pub use crate::linux::Keyboard;
+#[cfg(all(feature = "async", target_family = "unix", not(target_os = "macos")))]
+pub use crate::linux::{EventStream, listen_stream};
+#[cfg(all(
+    target_family = "unix",
+    not(target_os = "macos"),
+    feature = "wayland",
+    not(feature = "x11")
+))]
+pub use crate::linux::{OutputLayout, set_output_layout, set_pointer_position};
 #[cfg(all(target_family = "unix", not(target_os = "macos")))]
 use crate::linux::{display_size as _display_size, listen as _listen, simulate as _simulate};
 
@@ -269,21 +440,47 @@ pub fn listen<T>(callback: T) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
-    _listen(callback)
+    _listen(callback, &ListenHandle::new())
+}
+
+/// Same as `listen`, but the loop can be stopped from another thread with
+/// the given `ListenHandle`. Once stopped, this function returns `Ok(())`.
+///
+/// ```no_run
+/// use rdev::{listen_until, Event, ListenHandle};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let handle = ListenHandle::new();
+/// let stopper = handle.clone();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_secs(10));
+///     stopper.stop();
+/// });
+/// // This will block until `stopper.stop()` is called.
+/// if let Err(error) = listen_until(|event: Event| println!("{:?}", event), &handle) {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+pub fn listen_until<T>(callback: T, handle: &ListenHandle) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    _listen(callback, handle)
 }
 
 /// Sending some events
 ///
 /// ```no_run
-/// use rdev::{simulate, Button, EventType, Key, SimulateError};
+/// use rdev::{simulate, Button, EventType, Key};
 /// use std::{thread, time};
 ///
 /// fn send(event_type: &EventType) {
 ///     let delay = time::Duration::from_millis(20);
 ///     match simulate(event_type) {
 ///         Ok(()) => (),
-///         Err(SimulateError) => {
-///             println!("We could not send {:?}", event_type);
+///         Err(error) => {
+///             println!("We could not send {:?}: {}", event_type, error);
 ///         }
 ///     }
 ///     // Let the OS catchup (at least MacOS)
@@ -309,8 +506,68 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     Ok(())
 }
 
-/// Returns the size in pixels of the main screen.
+/// Types `text` with the keys of the active keyboard layout, holding Shift or
+/// AltGr for the characters on other levels and going through dead keys for
+/// composed ones. Characters the layout can't type are all reported with
+/// `SimulateError::UnmappedChars`, and nothing is typed then.
+/// The layout is probed with [`Keyboard`] on every call, Caps Lock is assumed off.
+/// On Wayland the layout is the keymap the compositor gives to its clients,
+/// `SimulateError::KeyboardError` is returned when it can't be asked for it.
+///
+/// Events are sent 20ms apart so that the OS catches up (MacOS drops or
+/// reorders them otherwise), see [`simulate_text_with_delay`] to change it.
+/// If an event fails, the modifiers still held are released before the error
+/// is returned.
+///
+/// ```no_run
+/// use rdev::simulate_text;
+///
+/// if let Err(error) = simulate_text("Hello, wörld!") {
+///     println!("We could not type the text: {}", error);
+/// }
+/// ```
+pub fn simulate_text(text: &str) -> Result<(), SimulateError> {
+    simulate_text_with_delay(text, std::time::Duration::from_millis(20))
+}
+
+/// Like [`simulate_text`], waiting `delay` after each event instead of 20ms.
+///
+/// ```no_run
+/// use rdev::simulate_text_with_delay;
+/// use std::time::Duration;
+///
+/// if let Err(error) = simulate_text_with_delay("Hello", Duration::from_millis(5)) {
+///     println!("We could not type the text: {}", error);
+/// }
+/// ```
+pub fn simulate_text_with_delay(
+    text: &str,
+    delay: std::time::Duration,
+) -> Result<(), SimulateError> {
+    #[cfg(all(
+        target_family = "unix",
+        not(target_os = "macos"),
+        feature = "wayland",
+        not(feature = "x11")
+    ))]
+    let mut keyboard = Keyboard::from_compositor()?;
+    #[cfg(not(all(
+        target_family = "unix",
+        not(target_os = "macos"),
+        feature = "wayland",
+        not(feature = "x11")
+    )))]
+    let mut keyboard = Keyboard::new().ok_or(SimulateError::KeyboardError)?;
+
+    let strokes = text::plan(&mut keyboard, text)?;
+    text::send(&strokes, delay, simulate)
+}
+
+/// Returns the size in pixels of the screen.
 /// This is useful to use with x, y from MouseMove Event.
+/// On Windows and MacOS it is the main display. On X11 it is the X screen,
+/// which spans every monitor. On Wayland it is the bounding box of all the
+/// outputs, in the compositor's logical pixels, asked to Sway or Hyprland.
 ///
 /// ```no_run
 /// use rdev::{display_size};
@@ -326,6 +583,9 @@ pub fn display_size() -> Result<(u64, u64), DisplayError> {
 #[cfg(all(target_family = "unix", not(target_os = "macos")))]
 pub use crate::linux::grab as _grab;
 #[cfg(feature = "unstable_grab")]
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+use crate::linux::grab_multi as _grab_multi;
+#[cfg(feature = "unstable_grab")]
 #[cfg(target_os = "macos")]
 pub use crate::macos::grab as _grab;
 #[cfg(feature = "unstable_grab")]
@@ -333,8 +593,10 @@ pub use crate::macos::grab as _grab;
 pub use crate::windows::grab as _grab;
 #[cfg(feature = "unstable_grab")]
 /// Grabbing global events. In the callback, returning None ignores the event
-/// and returning the event let's it pass. There is no modification of the event
-/// possible here.
+/// and returning the event let's it pass.
+/// On Linux, the returned event can be modified: it is translated back to evdev
+/// and sent instead of the original one (if it has no evdev equivalent, the
+/// original goes through). On Windows and MacOS, modifications are ignored.
 /// Caveat: On MacOS, you require the grab
 /// loop needs to be the primary app (no fork before) and need to have accessibility
 /// settings enabled.
@@ -361,7 +623,83 @@ pub fn grab<T>(callback: T) -> Result<(), GrabError>
 where
     T: FnMut(Event) -> Option<Event> + 'static,
 {
-    _grab(callback)
+    _grab(callback, &GrabHandle::new())
+}
+
+#[cfg(feature = "unstable_grab")]
+/// Same as `grab`, but the loop can be stopped from another thread with
+/// the given `GrabHandle`. Once stopped, devices are released and this
+/// function returns `Ok(())`.
+///
+/// ```no_run
+/// use rdev::{grab_until, Event, GrabHandle};
+///
+/// let handle = GrabHandle::new();
+/// let stopper = handle.clone();
+/// let callback = move |event: Event| -> Option<Event> {
+///     if event.name.as_deref() == Some("q") {
+///         stopper.stop();
+///     }
+///     Some(event)
+/// };
+/// // This will block until "q" is typed.
+/// if let Err(error) = grab_until(callback, &handle) {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+pub fn grab_until<T>(callback: T, handle: &GrabHandle) -> Result<(), GrabError>
+where
+    T: FnMut(Event) -> Option<Event> + 'static,
+{
+    _grab(callback, handle)
+}
+
+#[cfg(feature = "unstable_grab")]
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+/// Same as `grab`, but the callback returns every event to send in place of
+/// the grabbed one: an empty `Vec` swallows the event, returning the original
+/// event type lets it pass, anything else is sent instead (Linux only).
+/// Each returned event is written to the grabbed device's uinput copy in its
+/// own SYN frame, in order. Events that have no evdev equivalent are skipped,
+/// and the copy only accepts events the original device supports (a keyboard
+/// can't click).
+///
+/// ```no_run
+/// use rdev::{grab_multi, Event, EventType, Key};
+///
+/// // CapsLock becomes Ctrl+Space.
+/// let callback = |event: Event| -> Vec<EventType> {
+///     match event.event_type {
+///         EventType::KeyPress(Key::CapsLock) => vec![
+///             EventType::KeyPress(Key::ControlLeft),
+///             EventType::KeyPress(Key::Space),
+///             EventType::KeyRelease(Key::Space),
+///             EventType::KeyRelease(Key::ControlLeft),
+///         ],
+///         EventType::KeyRelease(Key::CapsLock) => vec![],
+///         event_type => vec![event_type],
+///     }
+/// };
+/// // This will block.
+/// if let Err(error) = grab_multi(callback) {
+///     println!("Error: {:?}", error)
+/// }
+/// ```
+pub fn grab_multi<T>(callback: T) -> Result<(), GrabError>
+where
+    T: FnMut(Event) -> Vec<EventType> + 'static,
+{
+    _grab_multi(callback, &GrabHandle::new())
+}
+
+#[cfg(feature = "unstable_grab")]
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+/// Same as `grab_multi`, stopped with the given `GrabHandle` like `grab_until`.
+pub fn grab_multi_until<T>(callback: T, handle: &GrabHandle) -> Result<(), GrabError>
+where
+    T: FnMut(Event) -> Vec<EventType> + 'static,
+{
+    _grab_multi(callback, handle)
 }
 
 #[cfg(test)]
@@ -423,4 +761,55 @@ mod tests {
         // assert_eq!(e, "é".to_string());
         // keyboard.add(&EventType::KeyRelease(Key::KeyE));
     }
+
+    #[test]
+    fn test_handle_wakers() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let handle = ListenHandle::new();
+        let woken = Arc::new(AtomicUsize::new(0));
+        let tokens: Vec<u64> = (0..2)
+            .map(|_| {
+                let woken = woken.clone();
+                handle.add_waker(move || {
+                    woken.fetch_add(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        // The first loop exits, the second one must still be woken up.
+        handle.remove_waker(tokens[0]);
+        handle.stop();
+        assert_eq!(woken.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_modifiers_update() {
+        let mut modifiers = crate::rdev::ModifierKeys::default();
+        modifiers.update(&EventType::KeyPress(Key::ControlLeft));
+        modifiers.update(&EventType::KeyPress(Key::CapsLock));
+        modifiers.update(&EventType::KeyRelease(Key::CapsLock));
+        let ctrl = modifiers.update(&EventType::KeyPress(Key::KeyS));
+        assert_eq!(ctrl, Modifiers::CTRL | Modifiers::CAPS_LOCK);
+        modifiers.update(&EventType::KeyRelease(Key::ControlLeft));
+        let caps_lock_off = modifiers.update(&EventType::KeyPress(Key::CapsLock));
+        assert_eq!(caps_lock_off, Modifiers::empty());
+
+        // Releasing one side keeps the modifier while the other side is held.
+        modifiers.update(&EventType::KeyPress(Key::ControlLeft));
+        modifiers.update(&EventType::KeyPress(Key::ControlRight));
+        let after_left = modifiers.update(&EventType::KeyRelease(Key::ControlLeft));
+        assert_eq!(after_left, Modifiers::CTRL);
+        let after_right = modifiers.update(&EventType::KeyRelease(Key::ControlRight));
+        assert_eq!(after_right, Modifiers::empty());
+
+        // Backends asking the system tell with `held` which keys are down.
+        let mut modifiers = Modifiers::SHIFT;
+        modifiers.update(&EventType::KeyRelease(Key::ShiftRight), |key| {
+            key == Key::ShiftLeft
+        });
+        assert_eq!(modifiers, Modifiers::SHIFT);
+        modifiers.update(&EventType::KeyRelease(Key::ShiftLeft), |_| false);
+        assert_eq!(modifiers, Modifiers::empty());
+    }
 }
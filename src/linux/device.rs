@@ -0,0 +1,62 @@
+#[cfg(feature = "unstable_grab")]
+use crate::rdev::GrabError;
+#[cfg(all(feature = "wayland", not(feature = "x11")))]
+use crate::rdev::SimulateError;
+use crate::rdev::{DeviceInfo, EventSource};
+use std::path::Path;
+use std::{fs, io};
+
+/// Identity of the uinput device the Wayland simulator creates.
+pub(crate) const RDEV_DEVICE_NAME: &str = "rdev virtual input";
+pub(crate) const RDEV_VENDOR_ID: u16 = 0x1234;
+pub(crate) const RDEV_PRODUCT_ID: u16 = 0x5678;
+
+pub(crate) const UINPUT_PATH: &str = "/dev/uinput";
+
+#[cfg(feature = "unstable_grab")]
+impl GrabError {
+    /// Error of opening `path`, an input device or uinput.
+    pub(crate) fn open(path: &Path, err: io::Error) -> GrabError {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => GrabError::PermissionDenied(path.into()),
+            io::ErrorKind::NotFound if path == Path::new(UINPUT_PATH) => {
+                GrabError::UinputUnavailable
+            }
+            _ => GrabError::IoError(err),
+        }
+    }
+}
+
+#[cfg(all(feature = "wayland", not(feature = "x11")))]
+impl SimulateError {
+    /// Error of opening uinput.
+    pub(crate) fn open_uinput(err: io::Error) -> SimulateError {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => SimulateError::PermissionDenied(UINPUT_PATH.into()),
+            io::ErrorKind::NotFound => SimulateError::UinputUnavailable,
+            _ => SimulateError::IoError(err),
+        }
+    }
+}
+
+/// Where events of a given input device come from: rdev's own uinput
+/// device, another virtual (uinput) device, or real hardware.
+pub(crate) fn device_source(info: &DeviceInfo) -> EventSource {
+    if info.name == RDEV_DEVICE_NAME
+        && info.vendor_id == RDEV_VENDOR_ID
+        && info.product_id == RDEV_PRODUCT_ID
+    {
+        return EventSource::Rdev;
+    }
+    let is_virtual = info
+        .sysname
+        .as_ref()
+        .and_then(|sysname| fs::canonicalize(Path::new("/sys/class/input").join(sysname)).ok())
+        .map(|path| path.starts_with("/sys/devices/virtual"))
+        .unwrap_or(false);
+    if is_virtual {
+        EventSource::OtherSynthetic
+    } else {
+        EventSource::Hardware
+    }
+}
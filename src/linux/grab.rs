@@ -0,0 +1,793 @@
+use crate::keycodes::evdev;
+use crate::linux::device::{UINPUT_PATH, device_source};
+use crate::linux::stop::StopFd;
+use crate::linux::touch::TouchFrame;
+use crate::rdev::{
+    Button, DeviceInfo, Event, EventSource, EventType, GrabError, GrabHandle, Key, KeyboardState,
+    ModifierKeys, RawCode, ScrollSource, SwitchKind,
+};
+use epoll::ControlOptions::{EPOLL_CTL_ADD, EPOLL_CTL_DEL};
+use evdev_rs::{
+    Device, DeviceWrapper, InputEvent, TimeVal, UInputDevice,
+    enums::{EV_KEY, EV_REL, EV_SW, EV_SYN, EventCode, int_to_ev_key, int_to_ev_sw},
+};
+use inotify::{Inotify, WatchMask};
+use std::ffi::{OsStr, OsString};
+use std::fs::{File, read_dir};
+use std::io;
+use std::os::unix::{
+    ffi::OsStrExt,
+    fs::FileTypeExt,
+    io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+// TODO The x, y coordinates are currently wrong !! Is there mouse acceleration
+// to take into account ??
+macro_rules! convert_buttons {
+    ($($ev_key:ident, $rdev_key:ident),*) => {
+        //TODO: make const when rust lang issue #49146 is fixed
+        fn evdev_key_to_rdev_button(key: &EV_KEY) -> Option<Button> {
+            match key {
+                $(
+                    EV_KEY::$ev_key => Some(Button::$rdev_key),
+                )*
+                _ => None,
+            }
+        }
+
+        //TODO: make const when rust lang issue #49146 is fixed
+        fn rdev_button_to_evdev_key(event: &Button) -> Option<EV_KEY> {
+            match event {
+                $(
+                    Button::$rdev_key => Some(EV_KEY::$ev_key),
+                )*
+                _ => None
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+convert_buttons!(
+    BTN_LEFT, Left,
+    BTN_RIGHT, Right,
+    BTN_MIDDLE, Middle,
+    BTN_SIDE, Back,
+    BTN_EXTRA, Forward
+);
+
+/// Keys without a name are passed through like other unknown events.
+fn evdev_key_to_rdev_key(key: &EV_KEY) -> Option<Key> {
+    match evdev::key_from_code(*key as u32) {
+        Key::Unknown(_) => None,
+        key => Some(key),
+    }
+}
+
+fn rdev_key_to_evdev_key(key: &Key) -> Option<EV_KEY> {
+    int_to_ev_key(evdev::code_from_key(*key)?)
+}
+
+fn evdev_event_to_rdev_event(
+    event: &InputEvent,
+    x: &mut f64,
+    y: &mut f64,
+    w: f64,
+    h: f64,
+) -> Option<EventType> {
+    match &event.event_code {
+        EventCode::EV_KEY(key) => {
+            if let Some(button) = evdev_key_to_rdev_button(key) {
+                // first check if pressed key is a mouse button
+                match event.value {
+                    0 => Some(EventType::ButtonRelease(button)),
+                    _ => Some(EventType::ButtonPress(button)),
+                }
+            } else if let Some(key) = evdev_key_to_rdev_key(key) {
+                // check if pressed key is a keyboard key
+                match event.value {
+                    0 => Some(EventType::KeyRelease(key)),
+                    2 => Some(EventType::KeyRepeat(key)),
+                    _ => Some(EventType::KeyPress(key)),
+                }
+            } else {
+                // if neither mouse button nor keyboard key, return none
+                None
+            }
+        }
+        EventCode::EV_REL(mouse) => match mouse {
+            EV_REL::REL_X => {
+                let dx = event.value as f64;
+                *x += dx;
+                if *x < 0.0 {
+                    *x = 0.0;
+                }
+                if *x > w {
+                    *x = w;
+                }
+                Some(EventType::MouseMove { x: *x, y: *y })
+            }
+            EV_REL::REL_Y => {
+                let dy = event.value as f64;
+                *y += dy;
+                if *y < 0.0 {
+                    *y = 0.0;
+                }
+                if *y > h {
+                    *y = h;
+                }
+                Some(EventType::MouseMove { x: *x, y: *y })
+            }
+            EV_REL::REL_HWHEEL => Some(EventType::Wheel {
+                delta_x: event.value.into(),
+                delta_y: 0,
+            }),
+            EV_REL::REL_WHEEL => Some(EventType::Wheel {
+                delta_x: 0,
+                delta_y: event.value.into(),
+            }),
+            // High resolution wheels send these along with the REL_WHEEL clicks.
+            EV_REL::REL_HWHEEL_HI_RES => Some(EventType::Scroll {
+                delta_x: event.value as f64 / 120.0,
+                delta_y: 0.0,
+                source: ScrollSource::Wheel,
+            }),
+            EV_REL::REL_WHEEL_HI_RES => Some(EventType::Scroll {
+                delta_x: 0.0,
+                delta_y: event.value as f64 / 120.0,
+                source: ScrollSource::Wheel,
+            }),
+            // Other EV_REL events cannot be represented by rdev
+            _ => None,
+        },
+        EventCode::EV_SW(switch) => {
+            let kind = match switch {
+                EV_SW::SW_LID => SwitchKind::Lid,
+                EV_SW::SW_TABLET_MODE => SwitchKind::TabletMode,
+                switch => SwitchKind::Unknown(*switch as u16),
+            };
+            Some(EventType::Switch {
+                kind,
+                on: event.value != 0,
+            })
+        }
+        // Other event_codes cannot be represented by rdev,
+        // and some never will e.g. EV_SYN
+        _ => None,
+    }
+}
+
+/// Translates an event returned by the grab callback back to evdev events.
+/// `x` and `y` are the tracked pointer position before the event, they are
+/// updated for mouse moves. Returns `None` if the event has no evdev equivalent.
+fn rdev_event_to_evdev_event(
+    event_type: &EventType,
+    time: &TimeVal,
+    x: &mut f64,
+    y: &mut f64,
+    w: f64,
+    h: f64,
+) -> Option<Vec<InputEvent>> {
+    let event = |code: EventCode, value: i32| InputEvent::new(time, &code, value);
+    match event_type {
+        EventType::KeyPress(key) => {
+            let key = rdev_key_to_evdev_key(key)?;
+            Some(vec![event(EventCode::EV_KEY(key), 1)])
+        }
+        EventType::KeyRelease(key) => {
+            let key = rdev_key_to_evdev_key(key)?;
+            Some(vec![event(EventCode::EV_KEY(key), 0)])
+        }
+        EventType::KeyRepeat(key) => {
+            let key = rdev_key_to_evdev_key(key)?;
+            Some(vec![event(EventCode::EV_KEY(key), 2)])
+        }
+        EventType::ButtonPress(button) => {
+            let button = rdev_button_to_evdev_key(button)?;
+            Some(vec![event(EventCode::EV_KEY(button), 1)])
+        }
+        EventType::ButtonRelease(button) => {
+            let button = rdev_button_to_evdev_key(button)?;
+            Some(vec![event(EventCode::EV_KEY(button), 0)])
+        }
+        EventType::MouseMove { x: new_x, y: new_y } => {
+            // evdev mice are relative, move by the difference with the tracked position.
+            let dx = (new_x.clamp(0.0, w) - *x).round() as i32;
+            let dy = (new_y.clamp(0.0, h) - *y).round() as i32;
+            *x += dx as f64;
+            *y += dy as f64;
+            let mut events = vec![];
+            if dx != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_X), dx));
+            }
+            if dy != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_Y), dy));
+            }
+            Some(events)
+        }
+        EventType::MouseMoveRelative { dx, dy } => {
+            let (dx, dy) = (dx.round() as i32, dy.round() as i32);
+            *x = (*x + dx as f64).clamp(0.0, w);
+            *y = (*y + dy as f64).clamp(0.0, h);
+            let mut events = vec![];
+            if dx != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_X), dx));
+            }
+            if dy != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_Y), dy));
+            }
+            Some(events)
+        }
+        EventType::Wheel { delta_x, delta_y } => {
+            let mut events = vec![];
+            if *delta_x != 0 {
+                events.push(event(
+                    EventCode::EV_REL(EV_REL::REL_HWHEEL),
+                    *delta_x as i32,
+                ));
+            }
+            if *delta_y != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_WHEEL), *delta_y as i32));
+            }
+            Some(events)
+        }
+        EventType::Scroll {
+            delta_x, delta_y, ..
+        } => {
+            let (delta_x, delta_y) = (
+                (delta_x * 120.0).round() as i32,
+                (delta_y * 120.0).round() as i32,
+            );
+            let mut events = vec![];
+            if delta_x != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_HWHEEL_HI_RES), delta_x));
+            }
+            if delta_y != 0 {
+                events.push(event(EventCode::EV_REL(EV_REL::REL_WHEEL_HI_RES), delta_y));
+            }
+            Some(events)
+        }
+        // Gestures are recognized by libinput, there are no evdev events for them.
+        EventType::Swipe { .. } | EventType::Pinch { .. } | EventType::Hold { .. } => None,
+        // Touches are in device units, see `TouchFrame`.
+        EventType::TouchDown { .. }
+        | EventType::TouchMove { .. }
+        | EventType::TouchUp { .. }
+        | EventType::TouchCancel { .. }
+        | EventType::TouchFrame => None,
+        // Tablets are passed through untouched, their events are not converted.
+        EventType::TabletProximity { .. }
+        | EventType::TabletTip { .. }
+        | EventType::TabletAxis { .. }
+        | EventType::TabletButton { .. }
+        | EventType::TabletPadButton { .. } => None,
+        EventType::Switch { kind, on } => {
+            let code = match kind {
+                SwitchKind::Lid => EV_SW::SW_LID,
+                SwitchKind::TabletMode => EV_SW::SW_TABLET_MODE,
+                SwitchKind::Unknown(code) => int_to_ev_sw((*code).into())?,
+            };
+            Some(vec![event(EventCode::EV_SW(code), (*on).into())])
+        }
+    }
+}
+
+/// Area the pointer moves in, in the coordinates events carry, and where the
+/// pointer starts. The X11 screen starts at 0, a Wayland output layout may not.
+pub(crate) struct Screen {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub pointer: (f64, f64),
+}
+
+/// Adapts a `grab` callback to `grab_multi`.
+pub(crate) fn single<T>(mut callback: T) -> impl FnMut(Event) -> Vec<EventType>
+where
+    T: FnMut(Event) -> Option<Event>,
+{
+    move |event| {
+        callback(event)
+            .map(|event| event.event_type)
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Grabs every evdev device, `kb` names the keys and `screen` bounds the pointer.
+pub(crate) fn grab_multi<K, T>(
+    mut kb: K,
+    screen: Screen,
+    mut callback: T,
+    handle: &GrabHandle,
+) -> Result<(), GrabError>
+where
+    K: KeyboardState,
+    T: FnMut(Event) -> Vec<EventType>,
+{
+    // The position is tracked from the screen origin, events carry screen coordinates.
+    let (origin_x, origin_y) = (screen.x, screen.y);
+    let mut x = screen.pointer.0 - origin_x;
+    let mut y = screen.pointer.1 - origin_y;
+    let w = screen.width - 1.0;
+    let h = screen.height - 1.0;
+    let mut modifiers = ModifierKeys::default();
+    filter_map_events(handle, |event, device| {
+        if let Some(touch) = &mut device.touch {
+            if touch.push(&event) {
+                return (vec![], GrabStatus::Continue);
+            }
+            if touch.is_pending(&event) {
+                let mut dropped = vec![];
+                let mut extra = vec![];
+                for (slot, event_type) in touch.take(screen.width, screen.height) {
+                    let event_type = offset(event_type, origin_x, origin_y);
+                    let rdev_event = Event {
+                        time: SystemTime::now(),
+                        timestamp: Some(timeval_to_duration(&event.time)),
+                        name: None,
+                        event_type,
+                        device: Some(Arc::clone(&device.info)),
+                        source: device.source,
+                        modifiers: modifiers.update(&event_type),
+                        raw_code: None,
+                    };
+                    let returned = callback(rdev_event);
+                    if returned == [event_type] {
+                        continue;
+                    }
+                    // Touches cannot be translated back, a changed touch is dropped.
+                    dropped.extend(slot);
+                    let returned = offset_all(&returned, -origin_x, -origin_y);
+                    extra.extend(rdev_events_to_evdev_frames(
+                        &returned,
+                        &event.time,
+                        &mut x,
+                        &mut y,
+                        w,
+                        h,
+                    ));
+                }
+                // The frame, then what the callback returned instead of the dropped touches.
+                let mut events = touch.drain_events(&dropped);
+                events.push(event);
+                events.extend(extra);
+                return (events, GrabStatus::Continue);
+            }
+        }
+        let (last_x, last_y) = (x, y);
+        let event_type = match evdev_event_to_rdev_event(&event, &mut x, &mut y, w, h) {
+            Some(rdev_event) => offset(rdev_event, origin_x, origin_y),
+            // If we can't convert event, simulate it
+            None => return (vec![event], GrabStatus::Continue),
+        };
+        let name = kb.add(&event_type);
+        let event_modifiers = modifiers.update(&event_type);
+        let raw_code = match (&event.event_code, event_type) {
+            (
+                EventCode::EV_KEY(key),
+                EventType::KeyPress(_) | EventType::KeyRelease(_) | EventType::KeyRepeat(_),
+            ) => Some(RawCode::Evdev(*key as u32)),
+            _ => None,
+        };
+        let rdev_event = Event {
+            time: SystemTime::now(),
+            timestamp: Some(timeval_to_duration(&event.time)),
+            name,
+            event_type,
+            device: Some(Arc::clone(&device.info)),
+            source: device.source,
+            modifiers: event_modifiers,
+            raw_code,
+        };
+        let returned = callback(rdev_event);
+        if returned == [event_type] {
+            // Unchanged, let the original event (and its device SYN) through.
+            return (vec![event], GrabStatus::Continue);
+        }
+        // The pointer only moves the way the returned events say.
+        (x, y) = (last_x, last_y);
+        let returned = offset_all(&returned, -origin_x, -origin_y);
+        let events = rdev_events_to_evdev_frames(&returned, &event.time, &mut x, &mut y, w, h);
+        (events, GrabStatus::Continue)
+    })
+}
+
+/// Translates the events returned by the grab callback, each in its own frame
+/// so that chords and sequences keep their order. Events without evdev
+/// equivalent are dropped.
+fn rdev_events_to_evdev_frames(
+    event_types: &[EventType],
+    time: &TimeVal,
+    x: &mut f64,
+    y: &mut f64,
+    w: f64,
+    h: f64,
+) -> Vec<InputEvent> {
+    let mut events = vec![];
+    for event_type in event_types {
+        if let Some(converted) = rdev_event_to_evdev_event(event_type, time, x, y, w, h) {
+            if converted.is_empty() {
+                continue;
+            }
+            events.extend(converted);
+            events.push(InputEvent::new(
+                time,
+                &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+                0,
+            ));
+        }
+    }
+    events
+}
+
+/// Moves positions between the screen and the grab coordinates, which start at 0.
+fn offset(event_type: EventType, dx: f64, dy: f64) -> EventType {
+    match event_type {
+        EventType::MouseMove { x, y } => EventType::MouseMove {
+            x: x + dx,
+            y: y + dy,
+        },
+        EventType::TouchDown { id, x, y } => EventType::TouchDown {
+            id,
+            x: x + dx,
+            y: y + dy,
+        },
+        EventType::TouchMove { id, x, y } => EventType::TouchMove {
+            id,
+            x: x + dx,
+            y: y + dy,
+        },
+        event_type => event_type,
+    }
+}
+
+fn offset_all(event_types: &[EventType], dx: f64, dy: f64) -> Vec<EventType> {
+    event_types
+        .iter()
+        .map(|event_type| offset(*event_type, dx, dy))
+        .collect()
+}
+
+/// An input device of the grab loop with what the loop knows about it.
+struct GrabDevice {
+    device: Device,
+    info: Arc<DeviceInfo>,
+    source: EventSource,
+    /// Multi-touch state, for touchscreens.
+    touch: Option<TouchFrame>,
+    /// The uinput copy events are sent to, `None` for devices plugged in while
+    /// grabbing, which are not grabbed.
+    output: Option<UInputDevice>,
+}
+
+impl GrabDevice {
+    fn new(path: PathBuf, device: Device) -> GrabDevice {
+        let info = DeviceInfo {
+            name: device.name().unwrap_or_default().to_string(),
+            vendor_id: device.vendor_id(),
+            product_id: device.product_id(),
+            sysname: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            path: Some(path),
+        };
+        GrabDevice {
+            source: device_source(&info),
+            info: Arc::new(info),
+            touch: TouchFrame::new(&device),
+            device,
+            output: None,
+        }
+    }
+}
+
+impl Drop for GrabDevice {
+    fn drop(&mut self) {
+        //ungrab devices, ignore errors
+        self.device.grab(evdev_rs::GrabMode::Ungrab).ok();
+    }
+}
+
+fn filter_map_events<F>(handle: &GrabHandle, mut func: F) -> Result<(), GrabError>
+where
+    F: FnMut(InputEvent, &mut GrabDevice) -> (Vec<InputEvent>, GrabStatus),
+{
+    // Everything is released when dropped, whichever way the loop exits.
+    let (epoll, mut devices) = setup_devices()?;
+    let epoll_fd = epoll.as_raw_fd();
+    let mut inotify = setup_inotify(epoll_fd, &devices)?;
+    let stop_fd = StopFd::new(handle)?;
+    let epoll_event = epoll::Event::new(EPOLLIN, STOP_DATA);
+    epoll::ctl(epoll_fd, EPOLL_CTL_ADD, stop_fd.as_raw_fd(), epoll_event)?;
+
+    //grab devices
+    devices
+        .iter_mut()
+        .try_for_each(|device| device.device.grab(evdev_rs::GrabMode::Grab))?;
+
+    // create buffer for epoll to fill
+    let mut epoll_buffer = [epoll::Event::new(epoll::Events::empty(), 0); 4];
+    let mut inotify_buffer = vec![0_u8; 4096];
+    'event_loop: while !handle.is_stopped() {
+        let num_events = epoll::wait(epoll_fd, -1, &mut epoll_buffer)?;
+
+        //map and simulate events, dealing with
+        'events: for event in &epoll_buffer[0..num_events] {
+            if event.data == STOP_DATA {
+                // `GrabHandle::stop` was called, the loop condition handles it.
+                continue;
+            }
+            // new device file created
+            if event.data == INOTIFY_DATA {
+                for event in inotify.read_events(&mut inotify_buffer)? {
+                    assert!(
+                        event.mask.contains(inotify::EventMask::CREATE),
+                        "inotify is listening for events other than file creation"
+                    );
+                    add_device_to_epoll_from_inotify_event(epoll_fd, event, &mut devices)?;
+                }
+            } else {
+                // Input device received event
+                let device = &mut devices[event.data as usize];
+                while device.device.has_event_pending() {
+                    //TODO: deal with EV_SYN::SYN_DROPPED
+                    let (_, event) = match device.device.next_event(evdev_rs::ReadFlag::NORMAL) {
+                        Ok(event) => event,
+                        Err(_) => {
+                            let device_fd = device.device.file().as_raw_fd();
+                            let empty_event = epoll::Event::new(epoll::Events::empty(), 0);
+                            epoll::ctl(epoll_fd, EPOLL_CTL_DEL, device_fd, empty_event)?;
+                            continue 'events;
+                        }
+                    };
+                    let (events, grab_status) = func(event, device);
+
+                    if let Some(output) = &device.output {
+                        for event in &events {
+                            output.write_event(event)?;
+                        }
+                    }
+                    if grab_status == GrabStatus::Stop {
+                        break 'event_loop;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+static DEV_PATH: &str = "/dev/input";
+const INOTIFY_DATA: u64 = u64::MAX;
+const STOP_DATA: u64 = u64::MAX - 1;
+const EPOLLIN: epoll::Events = epoll::Events::EPOLLIN;
+
+/// Whether to continue grabbing events or to stop
+/// Used in `filter_map_events` (and others)
+#[derive(Debug, Eq, PartialEq, Hash)]
+enum GrabStatus {
+    /// Stop grabbing
+    Continue,
+    /// ungrab events
+    Stop,
+}
+
+fn get_device_files<T>(path: T) -> Result<Vec<(PathBuf, File)>, GrabError>
+where
+    T: AsRef<Path>,
+{
+    let mut res = Vec::new();
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        // /dev/input files are character devices
+        if !entry.file_type()?.is_char_device() {
+            continue;
+        }
+
+        let path = entry.path();
+        let file_name_bytes = match path.file_name() {
+            Some(file_name) => file_name.as_bytes(),
+            None => continue, // file_name was "..", should be impossible
+        };
+        // skip filenames matching "mouse.* or mice".
+        // these files don't play nice with libevdev, not sure why
+        // see: https://askubuntu.com/questions/1043832/difference-between-dev-input-mouse0-and-dev-input-mice
+        if file_name_bytes == OsStr::new("mice").as_bytes()
+            || file_name_bytes
+                .get(0..=1)
+                .map(|s| s == OsStr::new("js").as_bytes())
+                .unwrap_or(false)
+            || file_name_bytes
+                .get(0..=4)
+                .map(|s| s == OsStr::new("mouse").as_bytes())
+                .unwrap_or(false)
+        {
+            continue;
+        }
+        let file = File::open(&path).map_err(|err| GrabError::open(&path, err))?;
+        res.push((path, file));
+    }
+    Ok(res)
+}
+
+fn epoll_watch_all<'a, T>(device_files: T) -> io::Result<OwnedFd>
+where
+    T: Iterator<Item = &'a File>,
+{
+    let epoll = unsafe { OwnedFd::from_raw_fd(epoll::create(true)?) };
+    // add file descriptors to epoll
+    for (file_idx, file) in device_files.enumerate() {
+        let epoll_event = epoll::Event::new(EPOLLIN, file_idx as u64);
+        epoll::ctl(
+            epoll.as_raw_fd(),
+            EPOLL_CTL_ADD,
+            file.as_raw_fd(),
+            epoll_event,
+        )?;
+    }
+    Ok(epoll)
+}
+
+fn inotify_devices() -> io::Result<Inotify> {
+    let inotify = Inotify::init()?;
+    inotify.watches().add(DEV_PATH, WatchMask::CREATE)?;
+    Ok(inotify)
+}
+
+fn add_device_to_epoll_from_inotify_event(
+    epoll_fd: RawFd,
+    event: inotify::Event<&OsStr>,
+    devices: &mut Vec<GrabDevice>,
+) -> Result<(), GrabError> {
+    let mut device_path = OsString::from(DEV_PATH);
+    device_path.push(OsString::from("/"));
+    device_path.push(event.name.unwrap());
+    // new plug events
+    let file =
+        File::open(&device_path).map_err(|err| GrabError::open(device_path.as_ref(), err))?;
+    let fd = file.as_raw_fd();
+    let device = Device::new_from_file(file)?;
+    device.set_clock_id(libc::CLOCK_MONOTONIC)?;
+    let event = epoll::Event::new(EPOLLIN, devices.len() as u64);
+    devices.push(GrabDevice::new(PathBuf::from(device_path), device));
+    epoll::ctl(epoll_fd, EPOLL_CTL_ADD, fd, event)?;
+    Ok(())
+}
+
+/// Devices are set to `CLOCK_MONOTONIC`, so this is the time since boot.
+fn timeval_to_duration(time: &TimeVal) -> Duration {
+    Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+}
+
+/// Returns the epoll instance and all devices, each with a uinput copy created
+/// from it. The epoll instance is level-triggered on any available data in the
+/// original devices, the epoll data is the index of the device.
+fn setup_devices() -> Result<(OwnedFd, Vec<GrabDevice>), GrabError> {
+    let device_files = get_device_files(DEV_PATH)?;
+    let epoll = epoll_watch_all(device_files.iter().map(|(_, file)| file))?;
+    let mut devices = vec![];
+    for (path, file) in device_files {
+        let device = Device::new_from_file(file)?;
+        device.set_clock_id(libc::CLOCK_MONOTONIC)?;
+        let mut device = GrabDevice::new(path, device);
+        let output = UInputDevice::create_from_device(&device.device)
+            .map_err(|err| GrabError::open(Path::new(UINPUT_PATH), err))?;
+        device.output = Some(output);
+        devices.push(device);
+    }
+    Ok((epoll, devices))
+}
+
+/// Creates an inotify instance looking at /dev/input and adds it to an epoll instance.
+/// Ensures devices isnt too long, which would make the epoll data ambigious.
+fn setup_inotify(epoll_fd: RawFd, devices: &[GrabDevice]) -> io::Result<Inotify> {
+    //Ensure there is space for inotify and the stop eventfd at last epoll indices.
+    if devices.len() as u64 >= STOP_DATA {
+        eprintln!("number of devices: {}", devices.len());
+        return Err(io::Error::other("too many device files!"));
+    }
+    // Set up inotify to listen for new devices being plugged in
+    let inotify = inotify_devices()?;
+    let epoll_event = epoll::Event::new(EPOLLIN, INOTIFY_DATA);
+    epoll::ctl(epoll_fd, EPOLL_CTL_ADD, inotify.as_raw_fd(), epoll_event)?;
+    Ok(inotify)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reversible_keys() {
+        for code in 0..=u16::MAX as u32 {
+            let Some(key) = evdev_rs::enums::int_to_ev_key(code) else {
+                continue;
+            };
+            if let Some(rdev_key) = evdev_key_to_rdev_key(&key) {
+                let evdev_key = rdev_key_to_evdev_key(&rdev_key).unwrap();
+                assert_eq!(evdev_key_to_rdev_key(&evdev_key), Some(rdev_key));
+            }
+            if let Some(button) = evdev_key_to_rdev_button(&key) {
+                let evdev_key = rdev_button_to_evdev_key(&button).unwrap();
+                assert_eq!(evdev_key_to_rdev_button(&evdev_key), Some(button));
+            }
+        }
+        assert_eq!(
+            evdev_key_to_rdev_button(&EV_KEY::BTN_SIDE),
+            Some(Button::Back)
+        );
+        assert_eq!(
+            evdev_key_to_rdev_button(&EV_KEY::BTN_EXTRA),
+            Some(Button::Forward)
+        );
+        // Every named key, as `keycodes::evdev` maps them.
+        assert_eq!(evdev_key_to_rdev_key(&EV_KEY::KEY_HOME), Some(Key::Home));
+        assert_eq!(
+            rdev_key_to_evdev_key(&Key::Unknown(EV_KEY::KEY_PROG1 as u32)),
+            Some(EV_KEY::KEY_PROG1)
+        );
+    }
+
+    #[test]
+    fn test_mouse_move_to_evdev() {
+        let time = TimeVal::new(0, 0);
+        let (mut x, mut y) = (10.0, 10.0);
+        let move_to = EventType::MouseMove { x: 15.0, y: 200.0 };
+        let events = rdev_event_to_evdev_event(&move_to, &time, &mut x, &mut y, 100.0, 100.0);
+        let events: Vec<_> = events
+            .unwrap()
+            .iter()
+            .map(|event| (event.event_code, event.value))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (EventCode::EV_REL(EV_REL::REL_X), 5),
+                (EventCode::EV_REL(EV_REL::REL_Y), 90)
+            ]
+        );
+        assert_eq!((x, y), (15.0, 100.0));
+    }
+
+    #[test]
+    fn test_key_repeat() {
+        let time = TimeVal::new(0, 0);
+        let (mut x, mut y) = (0.0, 0.0);
+        let event = InputEvent::new(&time, &EventCode::EV_KEY(EV_KEY::KEY_A), 2);
+        let event_type = evdev_event_to_rdev_event(&event, &mut x, &mut y, 100.0, 100.0);
+        assert_eq!(event_type, Some(EventType::KeyRepeat(Key::KeyA)));
+        let events =
+            rdev_event_to_evdev_event(&event_type.unwrap(), &time, &mut x, &mut y, 100.0, 100.0);
+        assert_eq!(events.unwrap()[0].value, 2);
+    }
+
+    #[test]
+    fn test_reversible_switches() {
+        let time = TimeVal::new(0, 0);
+        let (mut x, mut y) = (0.0, 0.0);
+        for (switch, kind) in [
+            (EV_SW::SW_LID, SwitchKind::Lid),
+            (EV_SW::SW_TABLET_MODE, SwitchKind::TabletMode),
+            (EV_SW::SW_HEADPHONE_INSERT, SwitchKind::Unknown(2)),
+        ] {
+            let event = InputEvent::new(&time, &EventCode::EV_SW(switch), 1);
+            let event_type = evdev_event_to_rdev_event(&event, &mut x, &mut y, 100.0, 100.0);
+            assert_eq!(event_type, Some(EventType::Switch { kind, on: true }));
+            let events = rdev_event_to_evdev_event(
+                &event_type.unwrap(),
+                &time,
+                &mut x,
+                &mut y,
+                100.0,
+                100.0,
+            );
+            assert_eq!(events.unwrap()[0].event_code, EventCode::EV_SW(switch));
+        }
+    }
+}
@@ -1,3 +1,16 @@
+#[cfg(any(
+    all(feature = "wayland", not(feature = "x11")),
+    feature = "unstable_grab"
+))]
+mod device;
+#[cfg(feature = "unstable_grab")]
+mod grab;
+mod stop;
+#[cfg(feature = "async")]
+mod stream;
+#[cfg(feature = "unstable_grab")]
+mod touch;
+
 #[cfg(feature = "x11")]
 mod x11;
 
@@ -10,5 +23,8 @@ mod wayland;
 #[cfg(all(feature = "wayland", not(feature = "x11")))]
 pub use wayland::*;
 
+#[cfg(feature = "async")]
+pub use stream::{EventStream, listen_stream};
+
 #[cfg(not(any(feature = "wayland", feature = "x11")))]
 compile_error!("Need to activate either wayland or x11 feature on linux");
@@ -0,0 +1,76 @@
+use crate::rdev::ListenHandle;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// An eventfd that becomes readable when the handle is stopped, so listeners
+/// can block on their input file descriptor instead of polling the handle.
+pub(crate) struct StopFd<'a> {
+    fd: OwnedFd,
+    handle: &'a ListenHandle,
+    waker: u64,
+}
+
+impl<'a> StopFd<'a> {
+    pub(crate) fn new(handle: &'a ListenHandle) -> io::Result<StopFd<'a>> {
+        let raw_fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if raw_fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+        let waker = handle.add_waker(move || {
+            let value: u64 = 1;
+            // The eventfd stays readable until it is closed, so a single write is enough.
+            unsafe { libc::write(raw_fd, &value as *const u64 as *const libc::c_void, 8) };
+        });
+        Ok(StopFd { fd, handle, waker })
+    }
+
+    /// Blocks until `fd` is readable, returns `false` if the handle was
+    /// stopped instead.
+    pub(crate) fn wait_readable(&self, fd: RawFd) -> io::Result<bool> {
+        let mut pollfds = [
+            libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        loop {
+            if self.handle.is_stopped() {
+                return Ok(false);
+            }
+            let result = unsafe { libc::poll(pollfds.as_mut_ptr(), 2, -1) };
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if pollfds[1].revents != 0 {
+                return Ok(false);
+            }
+            if pollfds[0].revents != 0 {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl AsRawFd for StopFd<'_> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Drop for StopFd<'_> {
+    fn drop(&mut self) {
+        // Unregister before `fd` gets closed so `stop` never writes to a reused fd.
+        self.handle.remove_waker(self.waker);
+    }
+}
@@ -0,0 +1,119 @@
+use super::Listener;
+use crate::rdev::{Event, ListenError};
+use async_io::Async;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// `Stream` of global events returned by `listen_stream`.
+/// Dropping the stream stops listening.
+/// On Linux it is not `Send`, see `listen_stream`.
+pub struct EventStream {
+    /// `None` once the listener failed.
+    listener: Option<Async<Listener>>,
+    /// Events of the last dispatch not polled yet.
+    events: VecDeque<Event>,
+    /// Whether the input may have data to dispatch.
+    ready: bool,
+    error: Option<ListenError>,
+}
+
+impl EventStream {
+    /// Stops listening.
+    /// Returns the error of the listener if it could not start or failed.
+    pub fn stop(mut self) -> Result<(), ListenError> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn fail(&mut self, error: ListenError) -> Poll<Option<Event>> {
+        self.listener = None;
+        self.error = Some(error);
+        Poll::Ready(None)
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            let this = &mut *self;
+            let Some(listener) = &mut this.listener else {
+                return Poll::Ready(None);
+            };
+            if !std::mem::take(&mut this.ready) {
+                // Registers the waker with the reactor until the input is readable.
+                match listener.poll_readable(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(err)) => return this.fail(ListenError::IoError(err)),
+                }
+            }
+            let events = &mut this.events;
+            // The fd of the listener stays the same, the reactor keeps watching it.
+            let result =
+                unsafe { listener.get_mut() }.dispatch(&mut |event| events.push_back(event));
+            if let Err(error) = result {
+                return this.fail(error);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("pending", &self.events.len())
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Listens to global events as a `futures::Stream`, so they can be awaited
+/// next to other async work. Requires the `async` feature.
+///
+/// No thread is involved: the libinput or X11 file descriptor is registered
+/// with the `async-io` reactor, and the input is read when the stream is
+/// polled. Events are only buffered one read at a time, a stream that isn't
+/// polled leaves the input to the kernel and the X server.
+/// If the listener can't start, the stream ends right away and `stop` returns
+/// the error.
+///
+/// libinput, xkb and Xlib must stay on the thread that opened them, so the
+/// stream is not `Send`: poll it on the thread calling `listen_stream`, e.g. in
+/// the main future of `#[tokio::main]` or a `tokio::task::LocalSet`, rather
+/// than in `tokio::spawn`.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use rdev::listen_stream;
+///
+/// # async fn run() {
+/// let mut events = listen_stream();
+/// while let Some(event) = events.next().await {
+///     println!("Received {:?}", event);
+/// }
+/// # }
+/// ```
+pub fn listen_stream() -> EventStream {
+    let listener =
+        Listener::new().and_then(|listener| Async::new(listener).map_err(ListenError::IoError));
+    let (listener, error) = match listener {
+        Ok(listener) => (Some(listener), None),
+        Err(error) => (None, Some(error)),
+    };
+    EventStream {
+        listener,
+        events: VecDeque::new(),
+        // libinput queues the devices present, Xlib may have buffered replies.
+        ready: true,
+        error,
+    }
+}
@@ -0,0 +1,392 @@
+use crate::rdev::EventType;
+use evdev_rs::enums::{EV_ABS, EV_KEY, EV_SYN, EventCode, InputProp};
+use evdev_rs::{Device, DeviceWrapper, InputEvent};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tag of buffered events that belong to every slot, like slot selections.
+const ANY_SLOT: i32 = -1;
+/// Tag of the single-touch emulation (`ABS_X`, `BTN_TOUCH`...) the kernel
+/// sends along with the slots, for clients that don't know multi-touch.
+const EMULATION: i32 = -2;
+
+/// Whether the event is part of the single-touch emulation of a touchscreen.
+fn is_emulation(code: &EventCode) -> bool {
+    matches!(
+        code,
+        EventCode::EV_ABS(
+            EV_ABS::ABS_X
+                | EV_ABS::ABS_Y
+                | EV_ABS::ABS_PRESSURE
+                | EV_ABS::ABS_DISTANCE
+                | EV_ABS::ABS_TOOL_WIDTH
+        ) | EventCode::EV_KEY(
+            EV_KEY::BTN_TOUCH
+                | EV_KEY::BTN_TOOL_FINGER
+                | EV_KEY::BTN_TOOL_DOUBLETAP
+                | EV_KEY::BTN_TOOL_TRIPLETAP
+                | EV_KEY::BTN_TOOL_QUADTAP
+                | EV_KEY::BTN_TOOL_QUINTTAP
+        )
+    )
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Change {
+    None,
+    Down,
+    Move,
+    Up,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Slot {
+    x: i32,
+    y: i32,
+    active: bool,
+    change: Change,
+    /// The previous touch went up before the `Down` of the same frame.
+    lifted: bool,
+}
+
+/// Multi-touch state of an evdev touchscreen (protocol B). The kernel only
+/// sends what changed in each slot, touches are known once the frame ends
+/// with SYN_REPORT, so the frame is held back until then.
+#[derive(Debug)]
+pub(crate) struct TouchFrame {
+    x_range: (i32, i32),
+    y_range: (i32, i32),
+    slot: i32,
+    slots: BTreeMap<i32, Slot>,
+    /// Held back events with the slot they apply to.
+    events: Vec<(i32, InputEvent)>,
+    /// Slots that went down in the frame being reported.
+    downs: Vec<i32>,
+    /// Slots whose touch was dropped when it went down, their events are
+    /// dropped until it goes up.
+    suppressed: BTreeSet<i32>,
+}
+
+impl TouchFrame {
+    /// `None` for devices other than touchscreens. Touchpads are multi-touch
+    /// too, but they move the pointer instead of being directly touched.
+    pub(crate) fn new(device: &Device) -> Option<TouchFrame> {
+        if !device.has_property(&InputProp::INPUT_PROP_DIRECT) {
+            return None;
+        }
+        let x = device.abs_info(&EventCode::EV_ABS(EV_ABS::ABS_MT_POSITION_X))?;
+        let y = device.abs_info(&EventCode::EV_ABS(EV_ABS::ABS_MT_POSITION_Y))?;
+        Some(TouchFrame {
+            x_range: (x.minimum, x.maximum),
+            y_range: (y.minimum, y.maximum),
+            slot: 0,
+            slots: BTreeMap::new(),
+            events: vec![],
+            downs: vec![],
+            suppressed: BTreeSet::new(),
+        })
+    }
+
+    /// Holds back multi-touch events and their single-touch emulation,
+    /// returns `false` for other events.
+    pub(crate) fn push(&mut self, event: &InputEvent) -> bool {
+        if is_emulation(&event.event_code) {
+            self.events.push((EMULATION, event.clone()));
+            return true;
+        }
+        let EventCode::EV_ABS(abs) = event.event_code else {
+            return false;
+        };
+        let slot = self.slots.entry(self.slot).or_insert(Slot {
+            x: 0,
+            y: 0,
+            active: false,
+            change: Change::None,
+            lifted: false,
+        });
+        match abs {
+            EV_ABS::ABS_MT_SLOT => {
+                self.slot = event.value;
+                self.events.push((ANY_SLOT, event.clone()));
+                return true;
+            }
+            EV_ABS::ABS_MT_TRACKING_ID if event.value < 0 => {
+                slot.active = false;
+                // Down and up in the same frame is nothing, but the touch
+                // lifted before that one still goes up.
+                slot.change = match slot.change {
+                    Change::Down if !slot.lifted => Change::None,
+                    _ => Change::Up,
+                };
+                slot.lifted = false;
+            }
+            EV_ABS::ABS_MT_TRACKING_ID => {
+                // A fast lift and touch again within one frame.
+                slot.lifted |=
+                    slot.change == Change::Up || (slot.active && slot.change != Change::Down);
+                slot.active = true;
+                slot.change = Change::Down;
+            }
+            EV_ABS::ABS_MT_POSITION_X | EV_ABS::ABS_MT_POSITION_Y => {
+                if abs == EV_ABS::ABS_MT_POSITION_X {
+                    slot.x = event.value;
+                } else {
+                    slot.y = event.value;
+                }
+                if slot.active && slot.change == Change::None {
+                    slot.change = Change::Move;
+                }
+            }
+            // Pressure, orientation... are kept with the touch they belong to.
+            abs if (abs as u32) >= (EV_ABS::ABS_MT_SLOT as u32) => {}
+            _ => return false,
+        }
+        self.events.push((self.slot, event.clone()));
+        true
+    }
+
+    /// Whether the end of frame has touches to report.
+    pub(crate) fn is_pending(&self, event: &InputEvent) -> bool {
+        event.event_code == EventCode::EV_SYN(EV_SYN::SYN_REPORT) && !self.events.is_empty()
+    }
+
+    /// Ends the frame: the touches that changed, by slot, in pixels of a `w` x `h`
+    /// screen, followed by `TouchFrame`. The slot of `TouchFrame` is `None`.
+    pub(crate) fn take(&mut self, w: f64, h: f64) -> Vec<(Option<i32>, EventType)> {
+        let scale = |value: i32, (min, max): (i32, i32), size: f64| {
+            (value - min) as f64 * size / (max - min + 1).max(1) as f64
+        };
+        let mut event_types = vec![];
+        for (&id, slot) in self.slots.iter_mut() {
+            let x = scale(slot.x, self.x_range, w);
+            let y = scale(slot.y, self.y_range, h);
+            if std::mem::take(&mut slot.lifted) {
+                event_types.push((Some(id), EventType::TouchUp { id: id as u32 }));
+                // Whether the new touch is dropped is up to the callback again.
+                self.suppressed.remove(&id);
+            }
+            let event_type = match slot.change {
+                Change::None => continue,
+                Change::Down => {
+                    self.downs.push(id);
+                    EventType::TouchDown {
+                        id: id as u32,
+                        x,
+                        y,
+                    }
+                }
+                Change::Move => EventType::TouchMove {
+                    id: id as u32,
+                    x,
+                    y,
+                },
+                Change::Up => EventType::TouchUp { id: id as u32 },
+            };
+            slot.change = Change::None;
+            event_types.push((Some(id), event_type));
+        }
+        event_types.push((None, EventType::TouchFrame));
+        event_types
+    }
+
+    /// The held back events, without those of the `dropped` slots nor of the
+    /// touches dropped when they went down. The emulation follows one of the
+    /// touches, it is dropped with them.
+    pub(crate) fn drain_events(&mut self, dropped: &[i32]) -> Vec<InputEvent> {
+        for slot in self.downs.drain(..) {
+            if dropped.contains(&slot) {
+                self.suppressed.insert(slot);
+            }
+        }
+        let is_dropped = |slot: &i32| dropped.contains(slot) || self.suppressed.contains(slot);
+        let dropping = self.events.iter().any(|(slot, _)| is_dropped(slot));
+        let events = self
+            .events
+            .drain(..)
+            .filter(|(slot, _)| match *slot {
+                EMULATION => !dropping,
+                _ => !is_dropped(slot),
+            })
+            .map(|(_, event)| event)
+            .collect();
+        // The up of a suppressed touch was dropped with it.
+        let slots = &self.slots;
+        self.suppressed
+            .retain(|slot| slots.get(slot).is_some_and(|slot| slot.active));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev_rs::TimeVal;
+
+    fn abs(code: EV_ABS, value: i32) -> InputEvent {
+        InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_ABS(code), value)
+    }
+
+    fn key(code: EV_KEY, value: i32) -> InputEvent {
+        InputEvent::new(&TimeVal::new(0, 0), &EventCode::EV_KEY(code), value)
+    }
+
+    fn touch_frame() -> TouchFrame {
+        TouchFrame {
+            x_range: (0, 999),
+            y_range: (0, 499),
+            slot: 0,
+            slots: BTreeMap::new(),
+            events: vec![],
+            downs: vec![],
+            suppressed: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_touch_frames() {
+        let mut frame = touch_frame();
+        assert!(frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 12)));
+        assert!(frame.push(&abs(EV_ABS::ABS_MT_POSITION_X, 500)));
+        assert!(frame.push(&abs(EV_ABS::ABS_MT_POSITION_Y, 250)));
+        assert!(frame.push(&abs(EV_ABS::ABS_X, 500)));
+        assert!(!frame.push(&key(EV_KEY::BTN_LEFT, 1)));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (
+                    Some(0),
+                    EventType::TouchDown {
+                        id: 0,
+                        x: 50.0,
+                        y: 50.0
+                    }
+                ),
+                (None, EventType::TouchFrame),
+            ]
+        );
+        assert_eq!(frame.drain_events(&[]).len(), 4);
+
+        // Second finger, the first one only moves vertically.
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_Y, 0));
+        frame.push(&abs(EV_ABS::ABS_MT_SLOT, 1));
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 13));
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_X, 0));
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_Y, 0));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (
+                    Some(0),
+                    EventType::TouchMove {
+                        id: 0,
+                        x: 50.0,
+                        y: 0.0
+                    }
+                ),
+                (
+                    Some(1),
+                    EventType::TouchDown {
+                        id: 1,
+                        x: 0.0,
+                        y: 0.0
+                    }
+                ),
+                (None, EventType::TouchFrame),
+            ]
+        );
+        // Without the second finger, the slot selection stays.
+        assert_eq!(frame.drain_events(&[1]).len(), 2);
+
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, -1));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (Some(1), EventType::TouchUp { id: 1 }),
+                (None, EventType::TouchFrame),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dropped_touch_down() {
+        let mut frame = touch_frame();
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 12));
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_X, 500));
+        frame.push(&key(EV_KEY::BTN_TOUCH, 1));
+        frame.push(&abs(EV_ABS::ABS_X, 500));
+        frame.take(100.0, 100.0);
+        // The emulation goes with the touch.
+        assert!(frame.drain_events(&[0]).is_empty());
+
+        // Later frames of the touch are dropped even if left unchanged.
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_X, 600));
+        frame.push(&abs(EV_ABS::ABS_X, 600));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (
+                    Some(0),
+                    EventType::TouchMove {
+                        id: 0,
+                        x: 60.0,
+                        y: 0.0
+                    }
+                ),
+                (None, EventType::TouchFrame),
+            ]
+        );
+        assert!(frame.drain_events(&[]).is_empty());
+
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, -1));
+        frame.push(&key(EV_KEY::BTN_TOUCH, 0));
+        frame.take(100.0, 100.0);
+        assert!(frame.drain_events(&[]).is_empty());
+
+        // The next touch of the slot goes through.
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 13));
+        frame.push(&key(EV_KEY::BTN_TOUCH, 1));
+        frame.take(100.0, 100.0);
+        assert_eq!(frame.drain_events(&[]).len(), 2);
+    }
+
+    #[test]
+    fn test_touch_lifted_and_down_again() {
+        let mut frame = touch_frame();
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 12));
+        frame.take(100.0, 100.0);
+        // The first touch is dropped.
+        frame.drain_events(&[0]);
+
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, -1));
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 13));
+        frame.push(&abs(EV_ABS::ABS_MT_POSITION_X, 500));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (Some(0), EventType::TouchUp { id: 0 }),
+                (
+                    Some(0),
+                    EventType::TouchDown {
+                        id: 0,
+                        x: 50.0,
+                        y: 0.0
+                    }
+                ),
+                (None, EventType::TouchFrame),
+            ]
+        );
+        // The new touch is not suppressed with the first one.
+        assert_eq!(frame.drain_events(&[]).len(), 3);
+
+        // Up, down and up again within one frame still ends the touch.
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, -1));
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, 14));
+        frame.push(&abs(EV_ABS::ABS_MT_TRACKING_ID, -1));
+        assert_eq!(
+            frame.take(100.0, 100.0),
+            vec![
+                (Some(0), EventType::TouchUp { id: 0 }),
+                (None, EventType::TouchFrame),
+            ]
+        );
+    }
+}
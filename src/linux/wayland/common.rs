@@ -1 +1,113 @@
+use super::pointer::OutputLayout;
+use serde::Deserialize;
+use std::process::Command;
 
+pub struct Display {}
+
+#[derive(Debug, Deserialize)]
+struct SwayDisplay {
+    active: bool,
+    rect: Rect,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct HyprDisplay {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    scale: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct HyprCursor {
+    x: f64,
+    y: f64,
+}
+
+/// Smallest layout containing every output.
+fn bounding_layout(rects: impl Iterator<Item = OutputLayout>) -> Option<OutputLayout> {
+    rects.reduce(|a, b| {
+        let x = a.x.min(b.x);
+        let y = a.y.min(b.y);
+        OutputLayout {
+            x,
+            y,
+            width: (a.x + a.width).max(b.x + b.width) - x,
+            height: (a.y + a.height).max(b.y + b.height) - y,
+        }
+    })
+}
+
+fn get_sway_layout() -> Option<OutputLayout> {
+    let output = Command::new("swaymsg")
+        .args(["-t", "get_outputs", "-r"])
+        .output()
+        .ok()?
+        .stdout;
+
+    let displays: Vec<SwayDisplay> = serde_json::from_slice(&output).ok()?;
+    // Sway already reports logical (scaled) rectangles.
+    bounding_layout(
+        displays
+            .into_iter()
+            .filter(|display| display.active)
+            .map(|display| OutputLayout {
+                x: display.rect.x,
+                y: display.rect.y,
+                width: display.rect.width,
+                height: display.rect.height,
+            }),
+    )
+}
+
+fn get_hyprland_layout() -> Option<OutputLayout> {
+    let output = Command::new("hyprctl")
+        .args(["monitors", "-j"])
+        .output()
+        .ok()?
+        .stdout;
+
+    let displays: Vec<HyprDisplay> = serde_json::from_slice(&output).ok()?;
+    // Hyprland reports modes in physical pixels but positions in logical ones.
+    bounding_layout(displays.into_iter().map(|display| OutputLayout {
+        x: display.x,
+        y: display.y,
+        width: (display.width / display.scale).round(),
+        height: (display.height / display.scale).round(),
+    }))
+}
+
+fn get_hyprland_cursor() -> Option<(f64, f64)> {
+    let output = Command::new("hyprctl")
+        .args(["cursorpos", "-j"])
+        .output()
+        .ok()?
+        .stdout;
+
+    let cursor: HyprCursor = serde_json::from_slice(&output).ok()?;
+    Some((cursor.x, cursor.y))
+}
+
+impl Display {
+    pub fn new() -> Option<Self> {
+        Some(Self {})
+    }
+
+    pub fn get_layout(&self) -> Option<OutputLayout> {
+        get_sway_layout().or_else(get_hyprland_layout)
+    }
+
+    /// Sway has no way to query the cursor, only Hyprland does.
+    pub fn get_mouse_pos(&self) -> Option<(f64, f64)> {
+        get_hyprland_cursor()
+    }
+}
@@ -1,7 +1,21 @@
+use super::common::Display;
+use super::pointer::OutputLayout;
 use crate::rdev::DisplayError;
 
+/// Layout of the outputs, asked to the compositor.
+pub(crate) fn output_layout() -> Result<OutputLayout, DisplayError> {
+    let display = Display::new().ok_or(DisplayError::NoDisplay)?;
+    display.get_layout().ok_or_else(|| {
+        // Not running under a compositor at all, rather than one we can't ask.
+        if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            DisplayError::NoDisplay
+        } else {
+            DisplayError::UnsupportedCompositor
+        }
+    })
+}
+
 pub fn display_size() -> Result<(u64, u64), DisplayError> {
-    todo!("Not implemented");
-    // let display = Display::new().ok_or(DisplayError::NoDisplay)?;
-    // display.get_size().ok_or(DisplayError::NoDisplay)
+    let layout = output_layout()?;
+    Ok((layout.width as u64, layout.height as u64))
 }
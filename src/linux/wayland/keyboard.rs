@@ -1,6 +1,9 @@
-use super::keycodes::code_from_key;
 use super::xkb_keycodes::internal_to_xkb_keycode;
-use crate::rdev::{EventType, Key, KeyboardState};
+use crate::keycodes::evdev::code_from_key;
+use crate::rdev::{EventType, Key, KeyboardState, SimulateError};
+use std::os::fd::OwnedFd;
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, QueueHandle, WEnum};
 use xkbcommon::xkb;
 
 #[allow(dead_code)]
@@ -10,14 +13,18 @@ pub struct Keyboard {
     context: xkb::Context,
     keymap: xkb::Keymap,
     state: xkb::State,
+    /// Dead keys and compose sequences, when the locale has a compose table.
+    compose: Option<xkb::compose::State>,
     shift: bool,
     caps_lock: bool,
     alt: bool,
+    alt_gr: bool,
     ctrl: bool,
     meta: bool,
     shift_idx: u32,
     caps_idx: u32,
     alt_idx: u32,
+    alt_gr_idx: u32,
     ctrl_idx: u32,
     meta_idx: u32,
     current_layout: String,
@@ -44,34 +51,74 @@ impl Keyboard {
             Some("terminate:ctrl_alt_bksp".to_string()),
             xkb::KEYMAP_COMPILE_NO_FLAGS,
         )
-        .ok_or(crate::rdev::SimulateError)?;
+        .ok_or(SimulateError::KeyboardError)?;
 
+        Ok(Self::with_keymap(context, keymap, layout, variant, model))
+    }
+
+    /// Keyboard with the keymap the compositor sends to its clients, which is
+    /// the one turning the keys of the uinput device into text.
+    pub(crate) fn from_compositor() -> Result<Self, SimulateError> {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = compositor_keymap(&context).ok_or(SimulateError::KeyboardError)?;
+        let layout = keymap.layout_get_name(0).to_string();
+        Ok(Self::with_keymap(
+            context,
+            keymap,
+            layout,
+            String::new(),
+            String::new(),
+        ))
+    }
+
+    fn with_keymap(
+        context: xkb::Context,
+        keymap: xkb::Keymap,
+        layout: String,
+        variant: String,
+        model: String,
+    ) -> Self {
         let state = xkb::State::new(&keymap);
         // Lookup modifier indices
         let shift_idx = keymap.mod_get_index("Shift");
         let caps_idx = keymap.mod_get_index("Lock");
         let alt_idx = keymap.mod_get_index("Mod1");
+        // ISO_Level3_Shift is on Mod5 with the usual keymaps.
+        let alt_gr_idx = keymap.mod_get_index("Mod5");
         let ctrl_idx = keymap.mod_get_index("Control");
         let meta_idx = keymap.mod_get_index("Mod4");
 
-        Ok(Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .find(|locale| !locale.is_empty())
+            .unwrap_or_else(|| "C".into());
+        let compose =
+            xkb::compose::Table::new_from_locale(&context, &locale, xkb::compose::COMPILE_NO_FLAGS)
+                .ok()
+                .map(|table| xkb::compose::State::new(&table, xkb::compose::STATE_NO_FLAGS));
+
+        Self {
             context,
             keymap,
             state,
+            compose,
             shift: false,
             caps_lock: false,
             alt: false,
+            alt_gr: false,
             ctrl: false,
             meta: false,
             shift_idx,
             caps_idx,
             alt_idx,
+            alt_gr_idx,
             ctrl_idx,
             meta_idx,
             current_layout: layout,
             current_variant: variant,
             current_model: model,
-        })
+        }
     }
 
     fn update_modifiers(&mut self) {
@@ -85,6 +132,9 @@ impl Keyboard {
         if self.alt {
             depressed |= 1 << self.alt_idx;
         }
+        if self.alt_gr {
+            depressed |= 1 << self.alt_gr_idx;
+        }
         if self.ctrl {
             depressed |= 1 << self.ctrl_idx;
         }
@@ -94,12 +144,30 @@ impl Keyboard {
         self.state.update_mask(depressed, 0, 0, 0, 0, 0);
     }
 
-    fn get_key_utf8(&self, keycode: u32) -> Option<String> {
+    fn get_key_utf8(&mut self, keycode: u32) -> Option<String> {
         let keycode = xkb::Keycode::from(keycode);
         let keysym = self.state.key_get_one_sym(keycode);
         if keysym == xkb::keysyms::KEY_NoSymbol.into() {
             return None;
         }
+        if let Some(compose) = &mut self.compose
+            && compose.feed(keysym) == xkb::compose::FeedResult::Accepted
+        {
+            match compose.status() {
+                // Like a dead key on the other platforms, it types nothing yet.
+                xkb::compose::Status::Composing => return Some(String::new()),
+                xkb::compose::Status::Composed => {
+                    let utf8 = compose.utf8();
+                    compose.reset();
+                    return utf8;
+                }
+                xkb::compose::Status::Cancelled => {
+                    compose.reset();
+                    return None;
+                }
+                xkb::compose::Status::Nothing => {}
+            }
+        }
         let utf8 = self.state.key_get_utf8(keycode);
         if utf8.is_empty() {
             return None;
@@ -111,6 +179,9 @@ impl Keyboard {
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
+            // A repeat types the character again, Caps Lock doesn't toggle again.
+            EventType::KeyRepeat(Key::CapsLock) => None,
+            EventType::KeyRepeat(key) => self.add(&EventType::KeyPress(*key)),
             EventType::KeyPress(key) => match key {
                 Key::ShiftLeft | Key::ShiftRight => {
                     self.shift = true;
@@ -127,6 +198,11 @@ impl KeyboardState for Keyboard {
                     self.update_modifiers();
                     None
                 }
+                Key::AltGr => {
+                    self.alt_gr = true;
+                    self.update_modifiers();
+                    None
+                }
                 Key::ControlLeft | Key::ControlRight => {
                     self.ctrl = true;
                     self.update_modifiers();
@@ -154,6 +230,11 @@ impl KeyboardState for Keyboard {
                     self.update_modifiers();
                     None
                 }
+                Key::AltGr => {
+                    self.alt_gr = false;
+                    self.update_modifiers();
+                    None
+                }
                 Key::ControlLeft | Key::ControlRight => {
                     self.ctrl = false;
                     self.update_modifiers();
@@ -174,8 +255,111 @@ impl KeyboardState for Keyboard {
         self.shift = false;
         self.caps_lock = false;
         self.alt = false;
+        self.alt_gr = false;
         self.ctrl = false;
         self.meta = false;
         self.update_modifiers();
+        if let Some(compose) = &mut self.compose {
+            compose.reset();
+        }
+    }
+}
+
+/// What the compositor told about its first seat.
+#[derive(Default)]
+struct SeatState {
+    seat: Option<wl_seat::WlSeat>,
+    has_keyboard: bool,
+    keymap: Option<(OwnedFd, u32)>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for SeatState {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        queue: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+            && interface == "wl_seat"
+            && state.seat.is_none()
+        {
+            state.seat = Some(registry.bind(name, version.min(7), queue, ()));
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for SeatState {
+    fn event(
+        state: &mut Self,
+        _: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            state.has_keyboard = capabilities.contains(wl_seat::Capability::Keyboard);
+        }
+    }
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for SeatState {
+    fn event(
+        state: &mut Self,
+        _: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_keyboard::Event::Keymap {
+            format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+            fd,
+            size,
+        } = event
+        {
+            state.keymap = Some((fd, size));
+        }
+    }
+}
+
+/// Keymap of the first seat, sent by the compositor as soon as its keyboard
+/// is bound. `None` without a compositor or a keyboard.
+fn compositor_keymap(context: &xkb::Context) -> Option<xkb::Keymap> {
+    let connection = Connection::connect_to_env().ok()?;
+    let mut queue = connection.new_event_queue();
+    let handle = queue.handle();
+    connection.display().get_registry(&handle, ());
+    let mut state = SeatState::default();
+    // The globals, then the capabilities of the seat bound meanwhile.
+    queue.roundtrip(&mut state).ok()?;
+    queue.roundtrip(&mut state).ok()?;
+    if !state.has_keyboard {
+        return None;
+    }
+    // The connection is closed on return, there is no need to release it.
+    state.seat.as_ref()?.get_keyboard(&handle, ());
+    queue.roundtrip(&mut state).ok()?;
+    let (fd, size) = state.keymap.take()?;
+    // The compositor sends a null terminated keymap of `size` bytes.
+    unsafe {
+        xkb::Keymap::new_from_fd(
+            context,
+            fd,
+            size as usize,
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
     }
+    .ok()?
 }
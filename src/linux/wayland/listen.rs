@@ -1,22 +1,372 @@
 extern crate libc;
 use super::keyboard::Keyboard;
-use super::keycodes::key_from_code;
-use crate::rdev::{Event, KeyboardState, ListenError};
+use super::pointer;
+use crate::keycodes::evdev::key_from_code;
+use crate::linux::device::device_source;
+use crate::linux::stop::StopFd;
+use crate::rdev::{
+    DeviceInfo, Event, EventSource, GesturePhase, KeyboardState, ListenError, ListenHandle,
+    ModifierKeys, RawCode, ScrollSource, SwitchKind, TabletAxes, ToolType,
+};
 use crate::{Button, EventType};
 use input::event::PointerEvent;
+use input::event::gesture::{
+    GestureEndEvent, GestureEvent, GestureEventCoordinates, GestureEventTrait, GestureHoldEvent,
+    GesturePinchEvent, GesturePinchEventTrait, GestureSwipeEvent,
+};
 use input::event::keyboard::{KeyState, KeyboardEventTrait};
-use input::event::pointer::{Axis, ButtonState};
-use input::{Event as LibEvent, Libinput, LibinputInterface};
+use input::event::pointer::{Axis, ButtonState, PointerEventTrait, PointerScrollEvent};
+use input::event::switch::{Switch, SwitchEvent, SwitchEventTrait, SwitchState};
+use input::event::tablet_pad::{TabletPadEvent, TabletPadEventTrait};
+use input::event::tablet_tool::{
+    ProximityState, TabletTool, TabletToolEvent, TabletToolEventTrait, TabletToolType, TipState,
+};
+use input::event::touch::{TouchEvent, TouchEventPosition, TouchEventSlot, TouchEventTrait};
+use input::event::{DeviceEvent, EventTrait};
+use input::{Device, Event as LibEvent, Libinput, LibinputInterface};
 use libc::{O_RDONLY, O_RDWR, O_WRONLY};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::os::unix::{fs::OpenOptionsExt, io::OwnedFd};
-use std::path::Path;
+use std::io;
+use std::os::unix::{
+    fs::OpenOptionsExt,
+    io::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-struct Interface;
+/// Opens the devices for libinput, remembering a refused one so that `listen`
+/// can tell why there are no devices.
+struct Interface {
+    denied: Rc<RefCell<Option<PathBuf>>>,
+}
+
+const SEAT: &str = "seat0";
+
+/// libinput stamps events with `CLOCK_MONOTONIC`.
+fn event_timestamp(libevent: &LibEvent) -> Option<Duration> {
+    match libevent {
+        LibEvent::Keyboard(key) => Some(Duration::from_micros(key.time_usec())),
+        LibEvent::Pointer(PointerEvent::Motion(motion)) => {
+            Some(Duration::from_micros(motion.time_usec()))
+        }
+        LibEvent::Pointer(PointerEvent::MotionAbsolute(motion)) => {
+            Some(Duration::from_micros(motion.time_usec()))
+        }
+        LibEvent::Pointer(PointerEvent::Button(btn)) => {
+            Some(Duration::from_micros(btn.time_usec()))
+        }
+        LibEvent::Pointer(PointerEvent::ScrollWheel(scroll)) => {
+            Some(Duration::from_micros(scroll.time_usec()))
+        }
+        LibEvent::Pointer(PointerEvent::ScrollFinger(scroll)) => {
+            Some(Duration::from_micros(scroll.time_usec()))
+        }
+        LibEvent::Pointer(PointerEvent::ScrollContinuous(scroll)) => {
+            Some(Duration::from_micros(scroll.time_usec()))
+        }
+        LibEvent::Gesture(gesture) => Some(Duration::from_micros(gesture.time_usec())),
+        LibEvent::Touch(touch) => Some(Duration::from_micros(touch.time_usec())),
+        LibEvent::Tablet(tablet) => Some(Duration::from_micros(tablet.time_usec())),
+        LibEvent::TabletPad(pad) => Some(Duration::from_micros(pad.time_usec())),
+        LibEvent::Switch(switch) => Some(Duration::from_micros(switch.time_usec())),
+        _ => None,
+    }
+}
+
+fn end_phase(end: &impl GestureEndEvent) -> GesturePhase {
+    if end.cancelled() {
+        GesturePhase::Cancel
+    } else {
+        GesturePhase::End
+    }
+}
+
+/// libinput finger counts are never negative.
+fn finger_count(gesture: &impl GestureEventTrait) -> u32 {
+    gesture.finger_count().max(0) as u32
+}
+
+/// Like `MotionAbsolute`, touches without the layout only have millimeters.
+fn convert_touch(touch: TouchEvent) -> Option<EventType> {
+    let position = |touch: &dyn TouchEventPosition| {
+        pointer::layout().map(|layout| {
+            (
+                layout.x + touch.x_transformed(layout.width as u32),
+                layout.y + touch.y_transformed(layout.height as u32),
+            )
+        })
+    };
+    match touch {
+        TouchEvent::Down(down) => {
+            let (x, y) = position(&down)?;
+            Some(EventType::TouchDown {
+                id: down.seat_slot(),
+                x,
+                y,
+            })
+        }
+        TouchEvent::Motion(motion) => {
+            let (x, y) = position(&motion)?;
+            Some(EventType::TouchMove {
+                id: motion.seat_slot(),
+                x,
+                y,
+            })
+        }
+        TouchEvent::Up(up) => Some(EventType::TouchUp { id: up.seat_slot() }),
+        TouchEvent::Cancel(cancel) => Some(EventType::TouchCancel {
+            id: cancel.seat_slot(),
+        }),
+        TouchEvent::Frame(_) => Some(EventType::TouchFrame),
+        _ => None,
+    }
+}
+
+fn tool_type(tool: &TabletTool) -> ToolType {
+    match tool.tool_type() {
+        Some(TabletToolType::Pen) => ToolType::Pen,
+        Some(TabletToolType::Eraser) => ToolType::Eraser,
+        Some(TabletToolType::Brush) => ToolType::Brush,
+        Some(TabletToolType::Pencil) => ToolType::Pencil,
+        Some(TabletToolType::Airbrush) => ToolType::Airbrush,
+        Some(TabletToolType::Mouse) => ToolType::Mouse,
+        Some(TabletToolType::Lens) => ToolType::Lens,
+        Some(TabletToolType::Totem) => ToolType::Totem,
+        _ => ToolType::Unknown,
+    }
+}
+
+/// Like `MotionAbsolute`, tablets without the layout only have millimeters.
+fn tablet_axes(tablet: &impl TabletToolEventTrait) -> Option<TabletAxes> {
+    let layout = pointer::layout()?;
+    Some(TabletAxes {
+        x: layout.x + tablet.x_transformed(layout.width as u32),
+        y: layout.y + tablet.y_transformed(layout.height as u32),
+        pressure: tablet.pressure(),
+        tilt_x: tablet.tilt_x(),
+        tilt_y: tablet.tilt_y(),
+    })
+}
+
+fn convert_tablet(tablet: TabletToolEvent) -> Option<EventType> {
+    let tool = tool_type(&tablet.tool());
+    match tablet {
+        TabletToolEvent::Proximity(proximity) => Some(EventType::TabletProximity {
+            tool,
+            in_proximity: proximity.proximity_state() == ProximityState::In,
+            // Leaving proximity still reports the last axes.
+            axes: tablet_axes(&proximity)?,
+        }),
+        TabletToolEvent::Tip(tip) => Some(EventType::TabletTip {
+            tool,
+            down: tip.tip_state() == TipState::Down,
+            axes: tablet_axes(&tip)?,
+        }),
+        TabletToolEvent::Axis(axis) => Some(EventType::TabletAxis {
+            tool,
+            axes: tablet_axes(&axis)?,
+        }),
+        TabletToolEvent::Button(button) => Some(EventType::TabletButton {
+            tool,
+            button: button.button(),
+            pressed: button.button_state() == ButtonState::Pressed,
+        }),
+        _ => None,
+    }
+}
+
+fn convert_tablet_pad(pad: TabletPadEvent) -> Option<EventType> {
+    match pad {
+        TabletPadEvent::Button(button) => Some(EventType::TabletPadButton {
+            button: button.button_number(),
+            pressed: button.button_state() == ButtonState::Pressed,
+        }),
+        // Rings and strips have no equivalent.
+        _ => None,
+    }
+}
+
+fn convert_switch(switch: SwitchEvent) -> Option<EventType> {
+    match switch {
+        SwitchEvent::Toggle(toggle) => {
+            let kind = match toggle.switch()? {
+                Switch::Lid => SwitchKind::Lid,
+                Switch::TabletMode => SwitchKind::TabletMode,
+                _ => return None,
+            };
+            Some(EventType::Switch {
+                kind,
+                on: toggle.switch_state() == SwitchState::On,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn convert_gesture(gesture: GestureEvent) -> Option<EventType> {
+    match gesture {
+        GestureEvent::Swipe(swipe) => {
+            let fingers = finger_count(&swipe);
+            let (phase, dx, dy) = match &swipe {
+                GestureSwipeEvent::Begin(_) => (GesturePhase::Begin, 0.0, 0.0),
+                GestureSwipeEvent::Update(update) => {
+                    (GesturePhase::Update, update.dx(), update.dy())
+                }
+                GestureSwipeEvent::End(end) => (end_phase(end), 0.0, 0.0),
+                _ => return None,
+            };
+            Some(EventType::Swipe {
+                phase,
+                fingers,
+                dx,
+                dy,
+            })
+        }
+        GestureEvent::Pinch(pinch) => {
+            let fingers = finger_count(&pinch);
+            let scale = pinch.scale();
+            let (phase, dx, dy, rotation) = match &pinch {
+                GesturePinchEvent::Begin(_) => (GesturePhase::Begin, 0.0, 0.0, 0.0),
+                GesturePinchEvent::Update(update) => (
+                    GesturePhase::Update,
+                    update.dx(),
+                    update.dy(),
+                    update.angle_delta(),
+                ),
+                GesturePinchEvent::End(end) => (end_phase(end), 0.0, 0.0, 0.0),
+                _ => return None,
+            };
+            Some(EventType::Pinch {
+                phase,
+                fingers,
+                dx,
+                dy,
+                scale,
+                rotation,
+            })
+        }
+        GestureEvent::Hold(hold) => {
+            let fingers = finger_count(&hold);
+            let phase = match &hold {
+                GestureHoldEvent::Begin(_) => GesturePhase::Begin,
+                GestureHoldEvent::End(end) => end_phase(end),
+                _ => return None,
+            };
+            Some(EventType::Hold { phase, fingers })
+        }
+        _ => None,
+    }
+}
+
+/// libinput reports a wheel click as 15 degrees, finger and continuous
+/// scrolling use the same scale so that they scroll alike.
+const UNITS_PER_NOTCH: f64 = 15.0;
+
+/// High resolution wheels send fractions of a click, `Wheel` is only
+/// reported once they add up to a whole one.
+#[derive(Debug, Default)]
+struct WheelRemainder {
+    x: f64,
+    y: f64,
+}
+
+impl WheelRemainder {
+    /// Adds a delta in v120 units (120 per click), returns the whole clicks.
+    fn add(&mut self, dx: f64, dy: f64) -> (i64, i64) {
+        fn add_axis(remainder: &mut f64, delta: f64) -> i64 {
+            // Changing direction starts over, like the kernel does.
+            if *remainder * delta < 0.0 {
+                *remainder = 0.0;
+            }
+            *remainder += delta;
+            let notches = (*remainder / 120.0).trunc();
+            *remainder -= notches * 120.0;
+            notches as i64
+        }
+        (add_axis(&mut self.x, dx), add_axis(&mut self.y, dy))
+    }
+}
+
+/// libinput scrolls down for positive vertical values, rdev up.
+fn scroll_values<E: PointerScrollEvent>(scroll: &E, value: impl Fn(&E, Axis) -> f64) -> (f64, f64) {
+    let axis = |axis| {
+        if scroll.has_axis(axis) {
+            value(scroll, axis)
+        } else {
+            0.0
+        }
+    };
+    (axis(Axis::Horizontal), -axis(Axis::Vertical))
+}
+
+/// Pointer motion gives both the relative move and the resulting position,
+/// hence several event types per libinput event. Likewise for scrolling.
+fn convert_type(libevent: LibEvent, wheel: &mut WheelRemainder) -> Vec<EventType> {
+    match libevent {
+        LibEvent::Pointer(PointerEvent::ScrollWheel(scroll)) => {
+            let (dx, dy) = scroll_values(&scroll, |scroll, axis| scroll.scroll_value_v120(axis));
+            let mut event_types = vec![EventType::Scroll {
+                delta_x: dx / 120.0,
+                delta_y: dy / 120.0,
+                source: ScrollSource::Wheel,
+            }];
+            let (delta_x, delta_y) = wheel.add(dx, dy);
+            if (delta_x, delta_y) != (0, 0) {
+                event_types.push(EventType::Wheel { delta_x, delta_y });
+            }
+            event_types
+        }
+        LibEvent::Pointer(PointerEvent::ScrollFinger(scroll)) => {
+            let (dx, dy) = scroll_values(&scroll, |scroll, axis| scroll.scroll_value(axis));
+            vec![EventType::Scroll {
+                delta_x: dx / UNITS_PER_NOTCH,
+                delta_y: dy / UNITS_PER_NOTCH,
+                source: ScrollSource::Finger,
+            }]
+        }
+        LibEvent::Pointer(PointerEvent::ScrollContinuous(scroll)) => {
+            let (dx, dy) = scroll_values(&scroll, |scroll, axis| scroll.scroll_value(axis));
+            vec![EventType::Scroll {
+                delta_x: dx / UNITS_PER_NOTCH,
+                delta_y: dy / UNITS_PER_NOTCH,
+                source: ScrollSource::Continuous,
+            }]
+        }
+        LibEvent::Pointer(PointerEvent::Motion(motion)) => {
+            let (dx, dy) = (motion.dx_unaccelerated(), motion.dy_unaccelerated());
+            // The compositor moves the cursor by the accelerated delta.
+            let (x, y) = pointer::move_by(motion.dx(), motion.dy());
+            vec![
+                EventType::MouseMoveRelative { dx, dy },
+                EventType::MouseMove { x, y },
+            ]
+        }
+        // Without the layout, we only have millimeters on the device.
+        LibEvent::Pointer(PointerEvent::MotionAbsolute(motion)) => match pointer::layout() {
+            Some(layout) => {
+                let (x, y) = pointer::move_to(
+                    layout.x + motion.absolute_x_transformed(layout.width as u32),
+                    layout.y + motion.absolute_y_transformed(layout.height as u32),
+                );
+                vec![EventType::MouseMove { x, y }]
+            }
+            None => vec![],
+        },
+        libevent => convert_single_type(libevent).into_iter().collect(),
+    }
+}
 
-fn convert_type(libevent: LibEvent) -> Option<EventType> {
+fn convert_single_type(libevent: LibEvent) -> Option<EventType> {
     match libevent {
+        LibEvent::Gesture(gesture) => convert_gesture(gesture),
+        LibEvent::Touch(touch) => convert_touch(touch),
+        LibEvent::Tablet(tablet) => convert_tablet(tablet),
+        LibEvent::TabletPad(pad) => convert_tablet_pad(pad),
+        LibEvent::Switch(switch) => convert_switch(switch),
         LibEvent::Keyboard(key) => {
             let k = key_from_code(key.key());
             let state: KeyState = key.key_state();
@@ -30,6 +380,10 @@ fn convert_type(libevent: LibEvent) -> Option<EventType> {
                 272 => Some(Button::Left),
                 273 => Some(Button::Right),
                 274 => Some(Button::Middle),
+                275 => Some(Button::Back),
+                276 => Some(Button::Forward),
+                // Other mouse buttons are numbered from BTN_MOUSE, like in `simulate`.
+                code @ 277..=287 => Some(Button::Unknown((code - 272) as u8)),
                 _ => None,
             };
             if let Some(rdev_btn) = rdev_btn {
@@ -42,33 +396,71 @@ fn convert_type(libevent: LibEvent) -> Option<EventType> {
                 None
             }
         }
-        LibEvent::Pointer(PointerEvent::Motion(btn)) => Some(EventType::MouseMove {
-            // TODO Convert to absolute X, Y
-            x: btn.dx_unaccelerated(),
-            y: btn.dy_unaccelerated(),
-        }),
-        LibEvent::Pointer(PointerEvent::MotionAbsolute(btn)) => Some(EventType::MouseMove {
-            x: btn.absolute_x(),
-            y: btn.absolute_y(),
-        }),
-        LibEvent::Pointer(PointerEvent::ScrollWheel(btn)) => Some(EventType::Wheel {
-            delta_x: -(btn.scroll_value_v120(Axis::Horizontal) / 120.0) as i64,
-            delta_y: -(btn.scroll_value_v120(Axis::Vertical) / 120.0) as i64,
-        }),
         _ => {
             // dbg!(format!("Received unhandlded event {lib:?}"));
             None
         }
     }
 }
-fn convert(keyboard: &mut Keyboard, libevent: LibEvent) -> Option<Event> {
-    let event_type = convert_type(libevent)?;
-    let name = keyboard.add(&event_type);
-    Some(Event {
-        time: SystemTime::now(),
-        name,
-        event_type,
-    })
+fn device_info(device: &Device) -> DeviceInfo {
+    DeviceInfo {
+        name: device.name().to_string(),
+        vendor_id: device.id_vendor() as u16,
+        product_id: device.id_product() as u16,
+        path: Some(Path::new("/dev/input").join(device.sysname())),
+        sysname: Some(device.sysname().to_string()),
+    }
+}
+
+/// Device infos by sysname, reading sysfs for the source on every event would be wasteful.
+type DeviceCache = HashMap<String, (Arc<DeviceInfo>, EventSource)>;
+
+fn convert(
+    keyboard: &mut Keyboard,
+    devices: &mut DeviceCache,
+    wheel: &mut WheelRemainder,
+    modifiers: &mut ModifierKeys,
+    libevent: LibEvent,
+) -> Vec<Event> {
+    if let LibEvent::Device(DeviceEvent::Removed(removed)) = &libevent {
+        // The sysname can be reused by the next plugged device.
+        devices.remove(removed.device().sysname());
+        return vec![];
+    }
+    let device = libevent.device();
+    let timestamp = event_timestamp(&libevent);
+    let raw_code = match &libevent {
+        LibEvent::Keyboard(key) => Some(RawCode::Evdev(key.key())),
+        _ => None,
+    };
+    let event_types = convert_type(libevent, wheel);
+    if event_types.is_empty() {
+        return vec![];
+    }
+    let sysname = device.sysname();
+    if !devices.contains_key(sysname) {
+        let info = device_info(&device);
+        let source = device_source(&info);
+        devices.insert(sysname.to_string(), (Arc::new(info), source));
+    }
+    let (info, source) = &devices[sysname];
+    let time = SystemTime::now();
+    event_types
+        .into_iter()
+        .map(|event_type| {
+            let event_modifiers = modifiers.update(&event_type);
+            Event {
+                time,
+                timestamp,
+                name: keyboard.add(&event_type),
+                event_type,
+                device: Some(Arc::clone(info)),
+                source: *source,
+                modifiers: event_modifiers,
+                raw_code,
+            }
+        })
+        .collect()
 }
 
 impl LibinputInterface for Interface {
@@ -80,27 +472,117 @@ impl LibinputInterface for Interface {
             .write((flags & O_WRONLY != 0) | (flags & O_RDWR != 0))
             .open(path)
             .map(|file| file.into())
-            .map_err(|err| err.raw_os_error().unwrap())
+            .map_err(|err| {
+                if err.kind() == io::ErrorKind::PermissionDenied {
+                    self.denied.borrow_mut().get_or_insert_with(|| path.into());
+                }
+                err.raw_os_error().unwrap_or(libc::EIO)
+            })
     }
     fn close_restricted(&mut self, fd: OwnedFd) {
         drop(File::from(fd));
     }
 }
 
-pub fn listen<T>(mut callback: T) -> Result<(), ListenError>
+/// Reads the devices of the seat through libinput.
+/// Neither libinput nor xkb can be used from another thread, so it is not `Send`.
+pub(crate) struct Listener {
+    input: Libinput,
+    denied: Rc<RefCell<Option<PathBuf>>>,
+    keyboard: Keyboard,
+    devices: DeviceCache,
+    wheel: WheelRemainder,
+    modifiers: ModifierKeys,
+    first_dispatch: bool,
+}
+
+impl Listener {
+    pub(crate) fn new() -> Result<Listener, ListenError> {
+        let denied = Rc::new(RefCell::new(None));
+        let mut input = Libinput::new_with_udev(Interface {
+            denied: denied.clone(),
+        });
+        input
+            .udev_assign_seat(SEAT)
+            .map_err(|()| ListenError::SeatError(SEAT.to_string()))?;
+        let keyboard = Keyboard::new().map_err(|_| ListenError::KeyboardError)?;
+        pointer::init();
+        Ok(Listener {
+            input,
+            denied,
+            keyboard,
+            devices: DeviceCache::new(),
+            wheel: WheelRemainder::default(),
+            modifiers: ModifierKeys::default(),
+            first_dispatch: true,
+        })
+    }
+
+    /// Passes the events of the input already available to `callback`,
+    /// without blocking.
+    pub(crate) fn dispatch(&mut self, callback: &mut impl FnMut(Event)) -> Result<(), ListenError> {
+        self.input.dispatch().map_err(ListenError::IoError)?;
+        let mut added = false;
+        for libevent in &mut self.input {
+            added |= matches!(libevent, LibEvent::Device(DeviceEvent::Added(_)));
+            for event in convert(
+                &mut self.keyboard,
+                &mut self.devices,
+                &mut self.wheel,
+                &mut self.modifiers,
+                libevent,
+            ) {
+                callback(event);
+            }
+        }
+        // The devices present are added by the first dispatch, without any
+        // the listener would wait forever.
+        if std::mem::take(&mut self.first_dispatch)
+            && !added
+            && let Some(path) = self.denied.borrow_mut().take()
+        {
+            return Err(ListenError::PermissionDenied(path));
+        }
+        Ok(())
+    }
+}
+
+impl AsFd for Listener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // libinput keeps its epoll fd open as long as the context.
+        unsafe { BorrowedFd::borrow_raw(self.input.as_raw_fd()) }
+    }
+}
+
+pub fn listen<T>(mut callback: T, handle: &ListenHandle) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
-    let mut input = Libinput::new_with_udev(Interface);
-    input.udev_assign_seat("seat0").unwrap();
-    let mut keyboard = Keyboard::new().map_err(|_| ListenError::KeyboardError)?;
+    let mut listener = Listener::new()?;
+    let stop_fd = StopFd::new(handle).map_err(ListenError::IoError)?;
+    let fd = listener.as_fd().as_raw_fd();
     loop {
-        input.dispatch().unwrap();
-        for libevent in &mut input {
-            if let Some(event) = convert(&mut keyboard, libevent) {
-                callback(event);
-            }
+        listener.dispatch(&mut callback)?;
+        if !stop_fd.wait_readable(fd).map_err(ListenError::IoError)? {
+            break;
         }
-        std::thread::sleep(Duration::from_millis(1));
+    }
+    // Dropping `listener` closes every device through `close_restricted`.
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_remainder() {
+        let mut wheel = WheelRemainder::default();
+        assert_eq!(wheel.add(0.0, 60.0), (0, 0));
+        assert_eq!(wheel.add(0.0, 90.0), (0, 1));
+        assert_eq!(wheel.add(-30.0, 250.0), (0, 2));
+        // Going back down forgets the partial click up.
+        assert_eq!(wheel.add(-100.0, -100.0), (-1, 0));
+        assert_eq!(wheel.add(0.0, -20.0), (0, -1));
     }
 }
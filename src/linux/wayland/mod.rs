@@ -5,14 +5,17 @@ mod display;
 #[cfg(feature = "unstable_grab")]
 mod grab;
 mod keyboard;
-mod keycodes;
 mod listen;
+mod pointer;
 mod simulate;
 mod xkb_keycodes;
 
 pub use self::display::display_size;
 #[cfg(feature = "unstable_grab")]
-pub use self::grab::grab;
+pub use self::grab::{grab, grab_multi};
 pub use self::keyboard::Keyboard;
+#[cfg(feature = "async")]
+pub(crate) use self::listen::Listener;
 pub use self::listen::listen;
+pub use self::pointer::{OutputLayout, set_output_layout, set_pointer_position};
 pub use self::simulate::simulate;
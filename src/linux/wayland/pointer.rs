@@ -0,0 +1,150 @@
+use super::common::Display;
+use std::sync::{Mutex, PoisonError};
+
+/// Area covered by the outputs, in the compositor's logical pixels.
+/// With several outputs this is the bounding box of all of them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OutputLayout {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl OutputLayout {
+    fn clamp(&self, x: f64, y: f64) -> (f64, f64) {
+        // `min` then `max` instead of `clamp`, which panics on empty layouts.
+        (
+            x.min(self.x + self.width - 1.0).max(self.x),
+            y.min(self.y + self.height - 1.0).max(self.y),
+        )
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (
+            (self.x + self.width / 2.0).floor(),
+            (self.y + self.height / 2.0).floor(),
+        )
+    }
+}
+
+/// Wayland has no global cursor position, relative motion is integrated here
+/// so that `MouseMove` carries absolute coordinates like on X11.
+#[derive(Debug)]
+struct Pointer {
+    layout: Option<OutputLayout>,
+    position: Option<(f64, f64)>,
+}
+
+impl Pointer {
+    fn position(&self) -> (f64, f64) {
+        self.position.unwrap_or((0.0, 0.0))
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) -> (f64, f64) {
+        let position = match self.layout {
+            Some(layout) => layout.clamp(x, y),
+            // Without a layout there are no edges to stop at.
+            None => (x, y),
+        };
+        self.position = Some(position);
+        position
+    }
+
+    fn move_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let (x, y) = self.position();
+        self.move_to(x + dx, y + dy)
+    }
+}
+
+static POINTER: Mutex<Pointer> = Mutex::new(Pointer {
+    layout: None,
+    position: None,
+});
+
+fn pointer() -> std::sync::MutexGuard<'static, Pointer> {
+    // The state is plain data, a panic while holding it cannot break it.
+    POINTER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Sets the output layout used to clamp the pointer position on Wayland.
+/// By default it is asked to the compositor (Sway and Hyprland) when listening starts.
+pub fn set_output_layout(layout: OutputLayout) {
+    let mut pointer = pointer();
+    pointer.layout = Some(layout);
+    if let Some((x, y)) = pointer.position {
+        pointer.move_to(x, y);
+    }
+}
+
+/// Sets the pointer position on Wayland, following `MouseMove` events
+/// start from there. By default it is asked to the compositor (Hyprland only),
+/// otherwise the pointer starts at the center of the layout.
+pub fn set_pointer_position(x: f64, y: f64) {
+    pointer().move_to(x, y);
+}
+
+/// Fills what the caller did not set from the compositor.
+pub(crate) fn init() {
+    let mut pointer = pointer();
+    let display = Display::new();
+    if pointer.layout.is_none() {
+        pointer.layout = display.as_ref().and_then(|display| display.get_layout());
+    }
+    if pointer.position.is_none() {
+        let position = display
+            .as_ref()
+            .and_then(|display| display.get_mouse_pos())
+            .or_else(|| pointer.layout.map(|layout| layout.center()));
+        if let Some((x, y)) = position {
+            pointer.move_to(x, y);
+        }
+    }
+}
+
+pub(crate) fn layout() -> Option<OutputLayout> {
+    pointer().layout
+}
+
+#[cfg_attr(not(feature = "unstable_grab"), allow(dead_code))]
+pub(crate) fn position() -> (f64, f64) {
+    pointer().position()
+}
+
+pub(crate) fn move_to(x: f64, y: f64) -> (f64, f64) {
+    pointer().move_to(x, y)
+}
+
+pub(crate) fn move_by(dx: f64, dy: f64) -> (f64, f64) {
+    pointer().move_by(dx, dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_clamped_to_layout() {
+        let mut pointer = Pointer {
+            layout: Some(OutputLayout {
+                x: -1920.0,
+                y: 0.0,
+                width: 3840.0,
+                height: 1080.0,
+            }),
+            position: Some((0.0, 540.0)),
+        };
+        assert_eq!(pointer.move_by(-100.0, 10.5), (-100.0, 550.5));
+        assert_eq!(pointer.move_by(-5000.0, 5000.0), (-1920.0, 1079.0));
+        assert_eq!(pointer.move_to(4000.0, -3.0), (1919.0, 0.0));
+    }
+
+    #[test]
+    fn test_move_without_layout() {
+        let mut pointer = Pointer {
+            layout: None,
+            position: None,
+        };
+        assert_eq!(pointer.move_by(-10.0, 20.0), (-10.0, 20.0));
+    }
+}
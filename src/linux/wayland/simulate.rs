@@ -1,14 +1,18 @@
+use super::display::output_layout;
+use super::pointer::{self, OutputLayout};
 use crate::SimulateError;
-use crate::linux::wayland::keycodes::ukey_from_key;
-use crate::rdev::{Button, EventType};
+use crate::keycodes::evdev::code_from_key;
+use crate::linux::device::{RDEV_DEVICE_NAME, RDEV_PRODUCT_ID, RDEV_VENDOR_ID, UINPUT_PATH};
+use crate::rdev::{Button, DisplayError, EventType, Key};
 use input_linux::{
-    EventKind, EventTime, InputEvent, InputId, Key as UKey, KeyEvent, KeyState, RelativeAxis,
-    RelativeEvent, SynchronizeEvent, SynchronizeKind, UInputHandle,
+    AbsoluteAxis, AbsoluteEvent, AbsoluteInfo, AbsoluteInfoSetup, EventKind, EventTime, InputEvent,
+    InputId, Key as UKey, KeyEvent, KeyState, RelativeAxis, RelativeEvent, SynchronizeEvent,
+    SynchronizeKind, UInputHandle,
 };
 use libc::{O_NONBLOCK, input_event};
 use std::fs::{File, OpenOptions};
 use std::os::unix::fs::OpenOptionsExt;
-use std::sync::{LazyLock, Mutex};
+use std::sync::{LazyLock, Mutex, PoisonError};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 static HANDLE: LazyLock<Mutex<Option<UInputHandle<File>>>> = LazyLock::new(|| Mutex::new(None));
@@ -16,42 +20,42 @@ static HANDLE: LazyLock<Mutex<Option<UInputHandle<File>>>> = LazyLock::new(|| Mu
 pub struct Handle;
 
 impl Handle {
-    pub fn new() -> Self {
-        let mut handle = HANDLE.lock().unwrap();
+    pub fn new() -> Result<Self, SimulateError> {
+        let mut handle = HANDLE.lock().unwrap_or_else(PoisonError::into_inner);
         if handle.is_none() {
             let file = OpenOptions::new()
                 .write(true)
                 .custom_flags(O_NONBLOCK)
-                .open("/dev/uinput")
-                .unwrap();
+                .open(UINPUT_PATH)
+                .map_err(SimulateError::open_uinput)?;
             let uinput = UInputHandle::new(file);
-            uinput.set_evbit(EventKind::Key).unwrap();
-            uinput.set_evbit(EventKind::Relative).unwrap();
-            uinput.set_relbit(RelativeAxis::X).unwrap();
-            uinput.set_relbit(RelativeAxis::Y).unwrap();
-            uinput.set_relbit(RelativeAxis::Wheel).unwrap();
-            uinput.set_relbit(RelativeAxis::WheelHiRes).unwrap();
+            uinput.set_evbit(EventKind::Key)?;
+            uinput.set_evbit(EventKind::Relative)?;
+            uinput.set_relbit(RelativeAxis::X)?;
+            uinput.set_relbit(RelativeAxis::Y)?;
+            uinput.set_relbit(RelativeAxis::Wheel)?;
+            uinput.set_relbit(RelativeAxis::WheelHiRes)?;
 
             // Enable all keys
             for key in UKey::iter() {
-                uinput.set_keybit(key).unwrap();
+                uinput.set_keybit(key)?;
             }
             // Enable all keys
             for rel in RelativeAxis::iter() {
-                uinput.set_relbit(rel).unwrap();
+                uinput.set_relbit(rel)?;
             }
 
             let input_id = InputId {
                 bustype: input_linux::sys::BUS_VIRTUAL,
-                vendor: 0x1234,
-                product: 0x5678,
+                vendor: RDEV_VENDOR_ID,
+                product: RDEV_PRODUCT_ID,
                 version: 1,
             };
-            let device_name = b"rdev virtual input";
-            uinput.create(&input_id, device_name, 0, &[]).unwrap();
+            let device_name = RDEV_DEVICE_NAME.as_bytes();
+            uinput.create(&input_id, device_name, 0, &[])?;
             *handle = Some(uinput);
         }
-        Handle
+        Ok(Handle)
     }
 
     fn get_current_time() -> EventTime {
@@ -73,87 +77,121 @@ impl Handle {
         let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
         let sync: input_event = InputEvent::from(sync).into();
 
-        handle.write(&[event, sync]).map_err(|_| SimulateError)?;
+        handle.write(&[event, sync])?;
+        Ok(())
+    }
+
+    /// Sends the non zero values in a single frame.
+    fn send_relative_events(
+        &self,
+        handle: &UInputHandle<File>,
+        values: &[(RelativeAxis, i32)],
+    ) -> Result<(), SimulateError> {
+        let time = Self::get_current_time();
+        let mut events: Vec<input_event> = values
+            .iter()
+            .filter(|(_, value)| *value != 0)
+            .map(|(axis, value)| InputEvent::from(RelativeEvent::new(time, *axis, *value)).into())
+            .collect();
+        if events.is_empty() {
+            return Ok(());
+        }
+        let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
+        events.push(InputEvent::from(sync).into());
+        handle.write(&events)?;
         Ok(())
     }
 
     pub fn send(&self, event: &EventType) -> Result<(), SimulateError> {
-        let handle = HANDLE.lock().unwrap();
+        let handle = HANDLE.lock().unwrap_or_else(PoisonError::into_inner);
         if let Some(handle) = handle.as_ref() {
             match event {
                 EventType::KeyPress(key) => {
-                    if let Some(ukey) = ukey_from_key(*key) {
-                        self.send_key_event(handle, ukey, KeyState::PRESSED)?;
-                    }
+                    let ukey = ukey_from_key(*key)?;
+                    self.send_key_event(handle, ukey, KeyState::PRESSED)?;
                 }
                 EventType::KeyRelease(key) => {
-                    if let Some(ukey) = ukey_from_key(*key) {
-                        self.send_key_event(handle, ukey, KeyState::RELEASED)?;
-                    }
+                    let ukey = ukey_from_key(*key)?;
+                    self.send_key_event(handle, ukey, KeyState::RELEASED)?;
+                }
+                EventType::KeyRepeat(key) => {
+                    let ukey = ukey_from_key(*key)?;
+                    self.send_key_event(handle, ukey, KeyState::AUTOREPEAT)?;
                 }
                 EventType::ButtonPress(button) => {
-                    let ukey = match button {
-                        Button::Left => UKey::ButtonLeft,
-                        Button::Right => UKey::ButtonRight,
-                        Button::Middle => UKey::ButtonMiddle,
-                        Button::Unknown(_) => return Err(SimulateError),
-                    };
+                    let ukey = ukey_from_button(*button)?;
                     let time = Self::get_current_time();
                     let event = KeyEvent::new(time, ukey, KeyState::PRESSED);
                     let event: input_event = InputEvent::from(event).into();
                     let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
                     let sync: input_event = InputEvent::from(sync).into();
-                    handle.write(&[event, sync]).map_err(|_| SimulateError)?;
+                    handle.write(&[event, sync])?;
                 }
                 EventType::ButtonRelease(button) => {
-                    let ukey = match button {
-                        Button::Left => UKey::ButtonLeft,
-                        Button::Right => UKey::ButtonRight,
-                        Button::Middle => UKey::ButtonMiddle,
-                        Button::Unknown(_) => return Err(SimulateError),
-                    };
+                    let ukey = ukey_from_button(*button)?;
                     let time = Self::get_current_time();
                     let event = KeyEvent::new(time, ukey, KeyState::RELEASED);
                     let event: input_event = InputEvent::from(event).into();
                     let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
                     let sync: input_event = InputEvent::from(sync).into();
-                    handle.write(&[event, sync]).map_err(|_| SimulateError)?;
+                    handle.write(&[event, sync])?;
                 }
                 EventType::MouseMove { x, y } => {
-                    let time = Self::get_current_time();
-                    // let reset_x = RelativeEvent::new(time, RelativeAxis::X, i32::MIN);
-                    // let reset_x: input_event = InputEvent::from(reset_x).into();
-                    // let reset_y = RelativeEvent::new(time, RelativeAxis::Y, i32::MIN);
-                    // let reset_y: input_event = InputEvent::from(reset_y).into();
-                    // let rsync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
-                    // let rsync: input_event = InputEvent::from(rsync).into();
-                    // handle
-                    //     .write(&[reset_x, reset_y, rsync])
-                    //     .map_err(|_| SimulateError)?;
-
-                    let event_x = RelativeEvent::new(time, RelativeAxis::X, *x as i32);
-                    let event_x: input_event = InputEvent::from(event_x).into();
-                    let event_y = RelativeEvent::new(time, RelativeAxis::Y, *y as i32);
-                    let event_y: input_event = InputEvent::from(event_y).into();
-                    let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
-                    let sync: input_event = InputEvent::from(sync).into();
-                    handle
-                        .write(&[event_x, event_y, sync])
-                        .map_err(|_| SimulateError)?;
+                    send_absolute_motion(*x, *y)?;
+                }
+                EventType::MouseMoveRelative { dx, dy } => {
+                    self.send_relative_events(
+                        handle,
+                        &[
+                            (RelativeAxis::X, dx.round() as i32),
+                            (RelativeAxis::Y, dy.round() as i32),
+                        ],
+                    )?;
                 }
                 EventType::Wheel { delta_x, delta_y } => {
-                    let time = Self::get_current_time();
-                    let event_x =
-                        RelativeEvent::new(time, RelativeAxis::WheelHiRes, (*delta_x * 120) as i32);
-                    let event_x: input_event = InputEvent::from(event_x).into();
-                    let event_y =
-                        RelativeEvent::new(time, RelativeAxis::WheelHiRes, (*delta_y * 120) as i32);
-                    let event_y: input_event = InputEvent::from(event_y).into();
-                    let sync = SynchronizeEvent::new(time, SynchronizeKind::Report, 0);
-                    let sync: input_event = InputEvent::from(sync).into();
-                    handle
-                        .write(&[event_x, event_y, sync])
-                        .map_err(|_| SimulateError)?;
+                    // Like a high resolution wheel, both resolutions for each click.
+                    let (delta_x, hi_res_x) = wheel_clicks(*delta_x)?;
+                    let (delta_y, hi_res_y) = wheel_clicks(*delta_y)?;
+                    self.send_relative_events(
+                        handle,
+                        &[
+                            (RelativeAxis::HorizontalWheel, delta_x),
+                            (RelativeAxis::HorizontalWheelHiRes, hi_res_x),
+                            (RelativeAxis::Wheel, delta_y),
+                            (RelativeAxis::WheelHiRes, hi_res_y),
+                        ],
+                    )?;
+                }
+                EventType::Swipe { .. }
+                | EventType::Pinch { .. }
+                | EventType::Hold { .. }
+                | EventType::TouchDown { .. }
+                | EventType::TouchMove { .. }
+                | EventType::TouchUp { .. }
+                | EventType::TouchCancel { .. }
+                | EventType::TouchFrame
+                | EventType::TabletProximity { .. }
+                | EventType::TabletTip { .. }
+                | EventType::TabletAxis { .. }
+                | EventType::TabletButton { .. }
+                | EventType::TabletPadButton { .. }
+                | EventType::Switch { .. } => {
+                    return Err(SimulateError::UnsupportedEvent(*event));
+                }
+                // Only the high resolution axes, `Wheel` is what sends whole clicks.
+                EventType::Scroll {
+                    delta_x, delta_y, ..
+                } => {
+                    self.send_relative_events(
+                        handle,
+                        &[
+                            (
+                                RelativeAxis::HorizontalWheelHiRes,
+                                (delta_x * 120.0).round() as i32,
+                            ),
+                            (RelativeAxis::WheelHiRes, (delta_y * 120.0).round() as i32),
+                        ],
+                    )?;
                 }
             }
         }
@@ -161,8 +199,120 @@ impl Handle {
     }
 }
 
+/// uinput key of the evdev code of `key`.
+/// Clicks of a wheel delta and their high resolution value, 120 per click.
+fn wheel_clicks(delta: i64) -> Result<(i32, i32), SimulateError> {
+    let clicks = i32::try_from(delta).map_err(|_| SimulateError::OutOfRange)?;
+    let hi_res = clicks.checked_mul(120).ok_or(SimulateError::OutOfRange)?;
+    Ok((clicks, hi_res))
+}
+
+fn ukey_from_key(key: Key) -> Result<UKey, SimulateError> {
+    code_from_key(key)
+        .and_then(|code| UKey::from_code(code.try_into().ok()?).ok())
+        .ok_or(SimulateError::UnmappedKey(key))
+}
+
+fn ukey_from_button(button: Button) -> Result<UKey, SimulateError> {
+    match button {
+        Button::Left => Ok(UKey::ButtonLeft),
+        Button::Right => Ok(UKey::ButtonRight),
+        Button::Middle => Ok(UKey::ButtonMiddle),
+        Button::Back => Ok(UKey::ButtonSide),
+        Button::Forward => Ok(UKey::ButtonExtra),
+        // Numbered from BTN_MOUSE, like in `listen`.
+        Button::Unknown(code) => UKey::from_code(UKey::ButtonLeft as u16 + code as u16)
+            .map_err(|_| SimulateError::UnmappedButton(button)),
+    }
+}
+
+/// Absolute positions go through a second uinput device, a tablet-like pointer
+/// whose axes span the output layout, the compositor maps it to the outputs.
+struct AbsoluteHandle {
+    uinput: UInputHandle<File>,
+    layout: OutputLayout,
+    /// Largest values of the axes, positive.
+    max_x: i32,
+    max_y: i32,
+}
+
+static ABS_HANDLE: LazyLock<Mutex<Option<AbsoluteHandle>>> = LazyLock::new(|| Mutex::new(None));
+
+fn create_absolute_handle() -> Result<AbsoluteHandle, SimulateError> {
+    // The layout set with `set_output_layout` wins over the compositor's.
+    let layout = match pointer::layout() {
+        Some(layout) => layout,
+        None => output_layout()?,
+    };
+    // Also rules out NaN, `clamp` below panics on an empty range.
+    if !(layout.width >= 1.0 && layout.height >= 1.0) {
+        return Err(SimulateError::Display(DisplayError::ConversionError));
+    }
+    let (max_x, max_y) = (layout.width as i32 - 1, layout.height as i32 - 1);
+    let file = OpenOptions::new()
+        .write(true)
+        .custom_flags(O_NONBLOCK)
+        .open(UINPUT_PATH)
+        .map_err(SimulateError::open_uinput)?;
+    let uinput = UInputHandle::new(file);
+    let setup = || -> std::io::Result<()> {
+        uinput.set_evbit(EventKind::Key)?;
+        // libinput only treats absolute devices with a button as pointers.
+        uinput.set_keybit(UKey::ButtonLeft)?;
+        uinput.set_evbit(EventKind::Absolute)?;
+        uinput.set_absbit(AbsoluteAxis::X)?;
+        uinput.set_absbit(AbsoluteAxis::Y)?;
+        let axis = |axis, maximum| AbsoluteInfoSetup {
+            axis,
+            info: AbsoluteInfo {
+                maximum,
+                ..Default::default()
+            },
+        };
+        let input_id = InputId {
+            bustype: input_linux::sys::BUS_VIRTUAL,
+            vendor: RDEV_VENDOR_ID,
+            product: RDEV_PRODUCT_ID,
+            version: 1,
+        };
+        uinput.create(
+            &input_id,
+            RDEV_DEVICE_NAME.as_bytes(),
+            0,
+            &[axis(AbsoluteAxis::X, max_x), axis(AbsoluteAxis::Y, max_y)],
+        )
+    };
+    setup()?;
+    Ok(AbsoluteHandle {
+        uinput,
+        layout,
+        max_x,
+        max_y,
+    })
+}
+
+fn send_absolute_motion(x: f64, y: f64) -> Result<(), SimulateError> {
+    let mut handle = ABS_HANDLE.lock().unwrap_or_else(PoisonError::into_inner);
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => handle.insert(create_absolute_handle()?),
+    };
+    // The axes start at the top left corner of the layout, not at 0, 0.
+    let x = ((x - handle.layout.x).round() as i32).clamp(0, handle.max_x);
+    let y = ((y - handle.layout.y).round() as i32).clamp(0, handle.max_y);
+    let time = Handle::get_current_time();
+    let event_x: input_event =
+        InputEvent::from(AbsoluteEvent::new(time, AbsoluteAxis::X, x)).into();
+    let event_y: input_event =
+        InputEvent::from(AbsoluteEvent::new(time, AbsoluteAxis::Y, y)).into();
+    let sync: input_event =
+        InputEvent::from(SynchronizeEvent::new(time, SynchronizeKind::Report, 0)).into();
+    handle.uinput.write(&[event_x, event_y, sync])?;
+    Ok(())
+}
+
 pub fn simulate(event: &EventType) -> Result<(), SimulateError> {
-    let handle = Handle::new();
+    let handle = Handle::new()?;
     handle.send(event)?;
     Ok(())
 }
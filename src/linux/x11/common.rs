@@ -1,32 +1,157 @@
 use super::keyboard::Keyboard;
-use super::keycodes::key_from_code;
-use crate::rdev::{Button, Event, EventType, KeyboardState};
+use crate::keycodes::x11::{code_from_key, key_from_code};
+use crate::rdev::{Button, Event, EventSource, EventType, KeyboardState, Modifiers, RawCode};
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::os::raw::{c_int, c_uchar, c_uint};
 use std::ptr::null;
-use std::time::SystemTime;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 use x11::xlib;
 
 pub const TRUE: c_int = 1;
 pub const FALSE: c_int = 0;
 
-// A global for the callbacks.
-pub static mut KEYBOARD: Option<Keyboard> = None;
+/// A request may generate no event (e.g. moving to the current position),
+/// so pending requests are bounded and expire.
+const MAX_PENDING_INPUTS: usize = 32;
+/// XTest events are generated while the server handles the request, a
+/// request older than that generated nothing.
+const FAKE_INPUT_EXPIRY_MS: u32 = 100;
+/// `simulate` and the listener see the request at about the same time.
+const RDEV_INPUT_EXPIRY: Duration = Duration::from_secs(1);
 
-pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<EventType> {
+/// An XTest request `simulate` sent from this process.
+struct RdevInput {
+    id: u64,
+    type_: u8,
+    detail: u8,
+    sent: Instant,
+}
+
+/// Requests of `simulate`, kept for every listener to claim rather than
+/// taken by the first one.
+struct RdevInputs {
+    next_id: u64,
+    inputs: VecDeque<RdevInput>,
+}
+
+static RDEV_INPUTS: Mutex<RdevInputs> = Mutex::new(RdevInputs {
+    next_id: 1,
+    inputs: VecDeque::new(),
+});
+
+pub fn push_rdev_input(type_: u8, detail: u8) {
+    let mut rdev = RDEV_INPUTS.lock().unwrap_or_else(PoisonError::into_inner);
+    if rdev.inputs.len() >= MAX_PENDING_INPUTS {
+        rdev.inputs.pop_front();
+    }
+    let id = rdev.next_id;
+    rdev.next_id += 1;
+    rdev.inputs.push_back(RdevInput {
+        id,
+        type_,
+        detail,
+        sent: Instant::now(),
+    });
+}
+
+/// An XTest request recorded by a listener, waiting for the device event it
+/// generates.
+struct FakeInput {
+    type_: u8,
+    detail: u8,
+    /// Server time of the request.
+    time: u32,
+    rdev: bool,
+}
+
+/// XTest requests recorded by one listener.
+#[derive(Default)]
+pub struct FakeInputs {
+    inputs: VecDeque<FakeInput>,
+    /// Last request of `simulate` this listener claimed, requests are seen in
+    /// the order they were sent.
+    rdev_claimed: u64,
+}
+
+impl FakeInputs {
+    /// Records a FakeInput request, telling whether `simulate` sent it.
+    pub fn push(&mut self, type_: u8, detail: u8, time: u32) {
+        let rdev = {
+            let rdev = RDEV_INPUTS.lock().unwrap_or_else(PoisonError::into_inner);
+            let claimed = rdev.inputs.iter().find(|input| {
+                input.id > self.rdev_claimed
+                    && input.type_ == type_
+                    && input.detail == detail
+                    && input.sent.elapsed() < RDEV_INPUT_EXPIRY
+            });
+            claimed.map(|input| input.id)
+        };
+        if let Some(id) = rdev {
+            self.rdev_claimed = id;
+        }
+        if self.inputs.len() >= MAX_PENDING_INPUTS {
+            self.inputs.pop_front();
+        }
+        self.inputs.push_back(FakeInput {
+            type_,
+            detail,
+            time,
+            rdev: rdev.is_some(),
+        });
+    }
+
+    /// The source of a device event, taking the request that generated it.
+    /// Motion details don't match between requests and events, only the
+    /// type is compared for them.
+    pub fn take(&mut self, type_: u8, detail: u8, time: u32) -> EventSource {
+        // Server time wraps around after 49 days.
+        self.inputs
+            .retain(|input| (time.wrapping_sub(input.time) as i32) <= FAKE_INPUT_EXPIRY_MS as i32);
+        let position = self.inputs.iter().position(|input| {
+            input.type_ == type_ && (type_ == xlib::MotionNotify as u8 || input.detail == detail)
+        });
+        match position.and_then(|index| self.inputs.remove(index)) {
+            Some(FakeInput { rdev: true, .. }) => EventSource::Rdev,
+            Some(FakeInput { rdev: false, .. }) => EventSource::OtherSynthetic,
+            None => EventSource::Hardware,
+        }
+    }
+}
+
+/// Keycodes held down, so that a press without a release in between is a repeat.
+/// The default XKB autorepeat records a release before each repeated press, it
+/// so those repeats come out as `KeyRelease` and `KeyPress`.
+pub type PressedKeys = [bool; 256];
+
+pub fn convert_event(
+    pressed: &mut PressedKeys,
+    code: c_uchar,
+    type_: c_int,
+    x: f64,
+    y: f64,
+) -> Option<EventType> {
     match type_ {
         xlib::KeyPress => {
             let key = key_from_code(code.into());
-            Some(EventType::KeyPress(key))
+            if std::mem::replace(&mut pressed[code as usize], true) {
+                Some(EventType::KeyRepeat(key))
+            } else {
+                Some(EventType::KeyPress(key))
+            }
         }
         xlib::KeyRelease => {
             let key = key_from_code(code.into());
+            pressed[code as usize] = false;
             Some(EventType::KeyRelease(key))
         }
         xlib::ButtonPress => match code {
             1 => Some(EventType::ButtonPress(Button::Left)),
             2 => Some(EventType::ButtonPress(Button::Middle)),
             3 => Some(EventType::ButtonPress(Button::Right)),
+            8 => Some(EventType::ButtonPress(Button::Back)),
+            9 => Some(EventType::ButtonPress(Button::Forward)),
             4 => Some(EventType::Wheel {
                 delta_y: 1,
                 delta_x: 0,
@@ -49,6 +174,8 @@ pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<Even
             1 => Some(EventType::ButtonRelease(Button::Left)),
             2 => Some(EventType::ButtonRelease(Button::Middle)),
             3 => Some(EventType::ButtonRelease(Button::Right)),
+            8 => Some(EventType::ButtonRelease(Button::Back)),
+            9 => Some(EventType::ButtonRelease(Button::Forward)),
             4 | 5 => None,
             _ => Some(EventType::ButtonRelease(Button::Unknown(code))),
         },
@@ -57,20 +184,59 @@ pub fn convert_event(code: c_uchar, type_: c_int, x: f64, y: f64) -> Option<Even
     }
 }
 
+/// Modifiers of an X event state, the state from before the event.
+pub fn modifiers_from_state(state: c_uint) -> Modifiers {
+    let masks = [
+        (xlib::ShiftMask, Modifiers::SHIFT),
+        (xlib::ControlMask, Modifiers::CTRL),
+        (xlib::Mod1Mask, Modifiers::ALT),
+        // ISO_Level3_Shift is on Mod5 with the usual layouts.
+        (xlib::Mod5Mask, Modifiers::ALT_GR),
+        (xlib::Mod4Mask, Modifiers::META),
+        (xlib::LockMask, Modifiers::CAPS_LOCK),
+        (xlib::Mod2Mask, Modifiers::NUM_LOCK),
+    ];
+    masks
+        .into_iter()
+        .filter(|(mask, _)| state & mask != 0)
+        .fold(Modifiers::empty(), |modifiers, (_, modifier)| {
+            modifiers | modifier
+        })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn convert(
-    keyboard: &mut Option<Keyboard>,
+    keyboard: &mut Keyboard,
+    pressed: &mut PressedKeys,
     code: c_uint,
     type_: c_int,
     x: f64,
     y: f64,
+    state: c_uint,
+    timestamp: Duration,
+    source: EventSource,
 ) -> Option<Event> {
-    let event_type = convert_event(code as c_uchar, type_, x, y)?;
-    let kb: &mut Keyboard = (*keyboard).as_mut()?;
-    let name = kb.add(&event_type);
+    let event_type = convert_event(pressed, code as c_uchar, type_, x, y)?;
+    let name = keyboard.add(&event_type);
+    let mut modifiers = modifiers_from_state(state);
+    modifiers.update(&event_type, |key| {
+        code_from_key(key).is_some_and(|code| pressed[code as usize])
+    });
+    let raw_code = match event_type {
+        EventType::KeyPress(_) | EventType::KeyRelease(_) | EventType::KeyRepeat(_) => {
+            Some(RawCode::X11(code))
+        }
+        _ => None,
+    };
     Some(Event {
         event_type,
         time: SystemTime::now(),
+        timestamp: Some(timestamp),
         name,
+        device: None,
+        source,
+        modifiers,
+        raw_code,
     })
 }
 
@@ -136,3 +302,37 @@ impl Drop for Display {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_inputs() {
+        let key_press = xlib::KeyPress as u8;
+        let mut first = FakeInputs::default();
+        let mut second = FakeInputs::default();
+
+        first.push(key_press, 200, 1000);
+        assert_eq!(first.take(key_press, 201, 1000), EventSource::Hardware);
+        assert_eq!(
+            first.take(key_press, 200, 1001),
+            EventSource::OtherSynthetic
+        );
+        assert_eq!(first.take(key_press, 200, 1001), EventSource::Hardware);
+
+        // A request that generated no event doesn't label later ones.
+        first.push(key_press, 200, 1000);
+        assert_eq!(first.take(key_press, 200, 2000), EventSource::Hardware);
+
+        // Every listener sees the requests of `simulate`.
+        push_rdev_input(key_press, 202);
+        first.push(key_press, 202, u32::MAX);
+        second.push(key_press, 202, u32::MAX);
+        assert_eq!(first.take(key_press, 202, 0), EventSource::Rdev);
+        assert_eq!(second.take(key_press, 202, 0), EventSource::Rdev);
+        // Claimed once per listener.
+        first.push(key_press, 202, 0);
+        assert_eq!(first.take(key_press, 202, 0), EventSource::OtherSynthetic);
+    }
+}
@@ -1,5 +1,5 @@
 extern crate x11;
-use super::keycodes::code_from_key;
+use crate::keycodes::x11::code_from_key;
 use crate::rdev::{EventType, Key, KeyboardState};
 use std::ffi::CString;
 use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
@@ -9,6 +9,7 @@ use x11::xlib;
 #[derive(Debug)]
 struct State {
     alt: bool,
+    alt_gr: bool,
     ctrl: bool,
     caps_lock: bool,
     shift: bool,
@@ -24,6 +25,7 @@ impl State {
     fn new() -> State {
         State {
             alt: false,
+            alt_gr: false,
             ctrl: false,
             caps_lock: false,
             meta: false,
@@ -36,6 +38,10 @@ impl State {
         if self.alt {
             res += xlib::Mod1Mask;
         }
+        // ISO_Level3_Shift is on Mod5 with the usual keymaps.
+        if self.alt_gr {
+            res += xlib::Mod5Mask;
+        }
         if self.ctrl {
             res += xlib::ControlMask;
         }
@@ -207,6 +213,9 @@ impl Keyboard {
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
+            // A repeat types the character again, Caps Lock doesn't toggle again.
+            EventType::KeyRepeat(Key::CapsLock) => None,
+            EventType::KeyRepeat(key) => self.add(&EventType::KeyPress(*key)),
             EventType::KeyPress(key) => match key {
                 Key::ShiftLeft | Key::ShiftRight => {
                     self.state.shift = true;
@@ -216,6 +225,10 @@ impl KeyboardState for Keyboard {
                     self.state.caps_lock = !self.state.caps_lock;
                     None
                 }
+                Key::AltGr => {
+                    self.state.alt_gr = true;
+                    None
+                }
                 key => {
                     let keycode = code_from_key(*key)?;
                     let state = self.state.value();
@@ -226,11 +239,22 @@ impl KeyboardState for Keyboard {
                 self.state.shift = false;
                 None
             }
+            EventType::KeyRelease(Key::AltGr) => {
+                self.state.alt_gr = false;
+                None
+            }
             _ => None,
         }
     }
     fn reset(&mut self) {
         self.state = State::new();
+        // Drops a pending dead key or compose sequence.
+        unsafe {
+            let pending = xlib::XmbResetIC(*self.xic);
+            if !pending.is_null() {
+                xlib::XFree(pending as *mut c_void);
+            }
+        }
     }
 }
 
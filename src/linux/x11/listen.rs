@@ -1,67 +1,175 @@
 extern crate libc;
 extern crate x11;
-use super::common::{FALSE, KEYBOARD, convert};
+use super::common::{FALSE, FakeInputs, PressedKeys, convert};
 use super::keyboard::Keyboard;
-use crate::rdev::{Event, ListenError};
+use crate::linux::stop::StopFd;
+use crate::rdev::{Event, ListenError, ListenHandle};
 use std::convert::TryInto;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
 use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ulong};
 use std::ptr::null;
+use std::time::Duration;
 use x11::xlib;
 use x11::xrecord;
 
-static mut RECORD_ALL_CLIENTS: c_ulong = xrecord::XRecordAllClients;
-static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+/// State of one listener, handed to `record_callback` as its closure so that
+/// listeners running at the same time don't share anything.
+struct Context {
+    keyboard: Keyboard,
+    pressed: PressedKeys,
+    fake_inputs: FakeInputs,
+    /// Events recorded by the last `XRecordProcessReplies`.
+    events: Vec<Event>,
+}
 
-pub fn listen<T>(callback: T) -> Result<(), ListenError>
-where
-    T: FnMut(Event) + 'static,
-{
-    let keyboard = Keyboard::new().ok_or(ListenError::KeyboardError)?;
+/// Records the device events through its own control display.
+/// Everything is released on drop, whatever step `new` stopped at.
+/// Xlib isn't initialized for threads, so it stays on the thread opening it.
+pub(crate) struct Listener {
+    display: *mut xlib::Display,
+    record: xrecord::XRecordContext,
+    enabled: bool,
+    context: *mut Context,
+}
 
-    unsafe {
-        KEYBOARD = Some(keyboard);
-        GLOBAL_CALLBACK = Some(Box::new(callback));
-        // Open displays
-        let dpy_control = xlib::XOpenDisplay(null());
-        if dpy_control.is_null() {
-            return Err(ListenError::MissingDisplayError);
-        }
-        let extension_name = c"RECORD";
-        let extension = xlib::XInitExtension(dpy_control, extension_name.as_ptr());
-        if extension.is_null() {
-            return Err(ListenError::XRecordExtensionError);
+impl Listener {
+    pub(crate) fn new() -> Result<Listener, ListenError> {
+        let keyboard = Keyboard::new().ok_or(ListenError::KeyboardError)?;
+        let context = Box::new(Context {
+            keyboard,
+            pressed: [false; 256],
+            fake_inputs: FakeInputs::default(),
+            events: vec![],
+        });
+
+        unsafe {
+            // Open displays
+            let mut listener = Listener {
+                display: xlib::XOpenDisplay(null()),
+                record: 0,
+                enabled: false,
+                context: Box::into_raw(context),
+            };
+            let dpy_control = listener.display;
+            if dpy_control.is_null() {
+                return Err(ListenError::MissingDisplayError);
+            }
+            let extension_name = c"RECORD";
+            let extension = xlib::XInitExtension(dpy_control, extension_name.as_ptr());
+            if extension.is_null() {
+                return Err(ListenError::XRecordExtensionError);
+            }
+
+            // Prepare record range
+            let record_range = xrecord::XRecordAllocRange();
+            if record_range.is_null() {
+                return Err(ListenError::RecordContextError);
+            }
+            (*record_range).device_events.first = xlib::KeyPress as c_uchar;
+            (*record_range).device_events.last = xlib::MotionNotify as c_uchar;
+            // Also record XTest FakeInput requests, to tell simulated events apart.
+            let (mut xtest_opcode, mut first_event, mut first_error) = (0, 0, 0);
+            let xtest_name = c"XTEST";
+            if xlib::XQueryExtension(
+                dpy_control,
+                xtest_name.as_ptr(),
+                &mut xtest_opcode,
+                &mut first_event,
+                &mut first_error,
+            ) != 0
+            {
+                let ext_major = &mut (*record_range).ext_requests.ext_major;
+                ext_major.first = xtest_opcode as c_uchar;
+                ext_major.last = xtest_opcode as c_uchar;
+                let ext_minor = &mut (*record_range).ext_requests.ext_minor;
+                ext_minor.first = X_XTEST_FAKE_INPUT;
+                ext_minor.last = X_XTEST_FAKE_INPUT;
+            }
+
+            // Create context
+            let mut clients = xrecord::XRecordAllClients;
+            let mut ranges = [record_range];
+            listener.record = xrecord::XRecordCreateContext(
+                dpy_control,
+                0,
+                &mut clients,
+                1,
+                ranges.as_mut_ptr(),
+                1,
+            );
+            xlib::XFree(record_range.cast());
+
+            if listener.record == 0 {
+                return Err(ListenError::RecordContextError);
+            }
+
+            xlib::XSync(dpy_control, FALSE);
+            // Run, the async version lets us check the handle between batches of events.
+            let result = xrecord::XRecordEnableContextAsync(
+                dpy_control,
+                listener.record,
+                Some(record_callback),
+                listener.context.cast(),
+            );
+            if result == 0 {
+                return Err(ListenError::RecordContextEnablingError);
+            }
+            listener.enabled = true;
+            Ok(listener)
         }
+    }
 
-        // Prepare record range
-        let mut record_range: xrecord::XRecordRange = *xrecord::XRecordAllocRange();
-        record_range.device_events.first = xlib::KeyPress as c_uchar;
-        record_range.device_events.last = xlib::MotionNotify as c_uchar;
-
-        // Create context
-        let ptr = &raw mut RECORD_ALL_CLIENTS;
-        let context = xrecord::XRecordCreateContext(
-            dpy_control,
-            0,
-            &mut *ptr,
-            1,
-            &mut &mut record_range as *mut &mut xrecord::XRecordRange
-                as *mut *mut xrecord::XRecordRange,
-            1,
-        );
-
-        if context == 0 {
-            return Err(ListenError::RecordContextError);
+    /// Passes the events of the replies already received to `callback`,
+    /// without blocking.
+    pub(crate) fn dispatch(&mut self, callback: &mut impl FnMut(Event)) -> Result<(), ListenError> {
+        let events = unsafe {
+            xrecord::XRecordProcessReplies(self.display);
+            std::mem::take(&mut (*self.context).events)
+        };
+        events.into_iter().for_each(callback);
+        Ok(())
+    }
+}
+
+impl AsFd for Listener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // The connection stays open as long as the listener.
+        unsafe { BorrowedFd::borrow_raw(xlib::XConnectionNumber(self.display)) }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.display.is_null() {
+                if self.enabled {
+                    xrecord::XRecordDisableContext(self.display, self.record);
+                }
+                if self.record != 0 {
+                    xrecord::XRecordFreeContext(self.display, self.record);
+                }
+                xlib::XCloseDisplay(self.display);
+            }
+            // `record_callback` can't run anymore.
+            drop(Box::from_raw(self.context));
         }
+    }
+}
 
-        xlib::XSync(dpy_control, FALSE);
-        // Run
-        let result =
-            xrecord::XRecordEnableContext(dpy_control, context, Some(record_callback), &mut 0);
-        if result == 0 {
-            return Err(ListenError::RecordContextEnablingError);
+pub fn listen<T>(mut callback: T, handle: &ListenHandle) -> Result<(), ListenError>
+where
+    T: FnMut(Event) + 'static,
+{
+    let mut listener = Listener::new()?;
+    let stop_fd = StopFd::new(handle).map_err(ListenError::IoError)?;
+    let fd = listener.as_fd().as_raw_fd();
+    loop {
+        // Xlib may already have buffered replies, process them before blocking.
+        listener.dispatch(&mut callback)?;
+        if !stop_fd.wait_readable(fd).map_err(ListenError::IoError)? {
+            return Ok(());
         }
     }
-    Ok(())
 }
 
 // No idea how to do that properly relevant doc lives here:
@@ -73,10 +181,11 @@ where
 struct XRecordDatum {
     type_: u8,
     code: u8,
-    _rest: u64,
-    _1: bool,
-    _2: bool,
-    _3: bool,
+    _sequence: u16,
+    time: u32,
+    _root: u32,
+    _event: u32,
+    _child: u32,
     root_x: i16,
     root_y: i16,
     event_x: i16,
@@ -84,13 +193,38 @@ struct XRecordDatum {
     state: u16,
 }
 
+/// Beginning of an XTest FakeInput request (xTestFakeInputReq).
+#[repr(C)]
+struct XTestFakeInputRequest {
+    _req_type: u8,
+    _xtest_req_type: u8,
+    _length: u16,
+    type_: u8,
+    detail: u8,
+}
+
+/// X_XTestFakeInput minor opcode.
+const X_XTEST_FAKE_INPUT: u16 = 2;
+
 unsafe extern "C" fn record_callback(
-    _null: *mut c_char,
+    context: *mut c_char,
     raw_data: *mut xrecord::XRecordInterceptData,
 ) {
     unsafe {
+        let context = &mut *(context as *mut Context);
         let data = raw_data.as_ref().unwrap();
+        if data.category == xrecord::XRecordFromClient
+            && data.data_len * 4 >= std::mem::size_of::<XTestFakeInputRequest>() as c_ulong
+        {
+            let request = (data.data as *const XTestFakeInputRequest)
+                .as_ref()
+                .unwrap();
+            context
+                .fake_inputs
+                .push(request.type_, request.detail, data.server_time as u32);
+        }
         if data.category != xrecord::XRecordFromServer {
+            xrecord::XRecordFreeData(raw_data);
             return;
         }
 
@@ -105,12 +239,24 @@ unsafe extern "C" fn record_callback(
         let x = xdatum.root_x as f64;
         let y = xdatum.root_y as f64;
 
-        let ptr = &raw mut KEYBOARD;
-        if let Some(event) = convert(&mut *ptr, code, type_, x, y) {
-            let ptr = &raw mut GLOBAL_CALLBACK;
-            if let Some(callback) = &mut *ptr {
-                callback(event);
-            }
+        let source = context
+            .fake_inputs
+            .take(xdatum.type_, xdatum.code, xdatum.time);
+
+        let timestamp = Duration::from_millis(xdatum.time.into());
+        let state = xdatum.state.into();
+        if let Some(event) = convert(
+            &mut context.keyboard,
+            &mut context.pressed,
+            code,
+            type_,
+            x,
+            y,
+            state,
+            timestamp,
+            source,
+        ) {
+            context.events.push(event);
         }
         xrecord::XRecordFreeData(raw_data);
     }
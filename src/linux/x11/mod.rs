@@ -6,13 +6,14 @@ mod display;
 #[cfg(feature = "unstable_grab")]
 mod grab;
 mod keyboard;
-mod keycodes;
 mod listen;
 mod simulate;
 
 pub use display::display_size;
 #[cfg(feature = "unstable_grab")]
-pub use grab::grab;
+pub use grab::{grab, grab_multi};
 pub use keyboard::Keyboard;
+#[cfg(feature = "async")]
+pub(crate) use listen::Listener;
 pub use listen::listen;
 pub use simulate::simulate;
@@ -1,37 +1,90 @@
-use super::common::{FALSE, TRUE};
-use super::keycodes::code_from_key;
-use crate::rdev::{Button, EventType, SimulateError};
-use std::os::raw::c_int;
+use super::common::{FALSE, TRUE, push_rdev_input};
+use crate::keycodes::x11::code_from_key;
+use crate::rdev::{Button, DisplayError, EventType, SimulateError};
+use std::os::raw::{c_int, c_uint};
 use std::ptr::null;
+use std::sync::{Mutex, PoisonError};
 use x11::xlib;
 use x11::xtest;
 
-unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Option<()> {
+/// Scroll not sent yet, in clicks: the core protocol only knows whole ones,
+/// so fractions add up across `Scroll` events until they make one.
+#[derive(Debug, Default, PartialEq)]
+struct ScrollRemainder {
+    x: f64,
+    y: f64,
+}
+
+impl ScrollRemainder {
+    /// Adds a delta in clicks, returns the whole clicks to send.
+    fn add(&mut self, dx: f64, dy: f64) -> (i64, i64) {
+        fn add_axis(remainder: &mut f64, delta: f64) -> i64 {
+            // Changing direction starts over, like the kernel does.
+            if *remainder * delta < 0.0 {
+                *remainder = 0.0;
+            }
+            *remainder += delta;
+            let clicks = remainder.trunc();
+            *remainder -= clicks;
+            clicks as i64
+        }
+        (add_axis(&mut self.x, dx), add_axis(&mut self.y, dy))
+    }
+}
+
+static SCROLL_REMAINDER: Mutex<ScrollRemainder> = Mutex::new(ScrollRemainder { x: 0.0, y: 0.0 });
+
+// Every XTest request is announced to the listener of this process first, so it can
+// mark the resulting event as simulated by rdev.
+unsafe fn fake_key(display: *mut xlib::Display, code: c_uint, is_press: c_int) -> c_int {
+    let type_ = if is_press == TRUE {
+        xlib::KeyPress
+    } else {
+        xlib::KeyRelease
+    };
+    push_rdev_input(type_ as u8, code as u8);
+    unsafe { xtest::XTestFakeKeyEvent(display, code, is_press, 0) }
+}
+
+unsafe fn fake_button(display: *mut xlib::Display, button: c_uint, is_press: c_int) -> c_int {
+    let type_ = if is_press == TRUE {
+        xlib::ButtonPress
+    } else {
+        xlib::ButtonRelease
+    };
+    push_rdev_input(type_ as u8, button as u8);
+    unsafe { xtest::XTestFakeButtonEvent(display, button, is_press, 0) }
+}
+
+unsafe fn send_native(
+    event_type: &EventType,
+    display: *mut xlib::Display,
+) -> Result<(), SimulateError> {
     unsafe {
         let res = match event_type {
-            EventType::KeyPress(key) => {
-                let code = code_from_key(*key)?;
-                xtest::XTestFakeKeyEvent(display, code, TRUE, 0)
+            EventType::KeyPress(key) | EventType::KeyRepeat(key) => {
+                let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
+                fake_key(display, code, TRUE)
             }
             EventType::KeyRelease(key) => {
-                let code = code_from_key(*key)?;
-                xtest::XTestFakeKeyEvent(display, code, FALSE, 0)
+                let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
+                fake_key(display, code, FALSE)
             }
             EventType::ButtonPress(button) => match button {
-                Button::Left => xtest::XTestFakeButtonEvent(display, 1, TRUE, 0),
-                Button::Middle => xtest::XTestFakeButtonEvent(display, 2, TRUE, 0),
-                Button::Right => xtest::XTestFakeButtonEvent(display, 3, TRUE, 0),
-                Button::Unknown(code) => {
-                    xtest::XTestFakeButtonEvent(display, (*code).into(), TRUE, 0)
-                }
+                Button::Left => fake_button(display, 1, TRUE),
+                Button::Middle => fake_button(display, 2, TRUE),
+                Button::Right => fake_button(display, 3, TRUE),
+                Button::Back => fake_button(display, 8, TRUE),
+                Button::Forward => fake_button(display, 9, TRUE),
+                Button::Unknown(code) => fake_button(display, (*code).into(), TRUE),
             },
             EventType::ButtonRelease(button) => match button {
-                Button::Left => xtest::XTestFakeButtonEvent(display, 1, FALSE, 0),
-                Button::Middle => xtest::XTestFakeButtonEvent(display, 2, FALSE, 0),
-                Button::Right => xtest::XTestFakeButtonEvent(display, 3, FALSE, 0),
-                Button::Unknown(code) => {
-                    xtest::XTestFakeButtonEvent(display, (*code).into(), FALSE, 0)
-                }
+                Button::Left => fake_button(display, 1, FALSE),
+                Button::Middle => fake_button(display, 2, FALSE),
+                Button::Right => fake_button(display, 3, FALSE),
+                Button::Back => fake_button(display, 8, FALSE),
+                Button::Forward => fake_button(display, 9, FALSE),
+                Button::Unknown(code) => fake_button(display, (*code).into(), FALSE),
             },
             EventType::MouseMove { x, y } => {
                 //TODO: replace with clamp if it is stabalized
@@ -45,9 +98,20 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
                 } else {
                     0
                 };
+                push_rdev_input(xlib::MotionNotify as u8, 0);
                 xtest::XTestFakeMotionEvent(display, 0, x, y, 0)
                 //     xlib::XWarpPointer(display, 0, root, 0, 0, 0, 0, *x as i32, *y as i32);
             }
+            EventType::MouseMoveRelative { dx, dy } => {
+                push_rdev_input(xlib::MotionNotify as u8, 1);
+                xtest::XTestFakeRelativeMotionEvent(
+                    display,
+                    dx.round() as c_int,
+                    dy.round() as c_int,
+                    0,
+                    0,
+                )
+            }
             EventType::Wheel { delta_x, delta_y } => {
                 let code_x = if *delta_x > 0 { 7 } else { 6 };
                 let code_y = if *delta_y > 0 { 4 } else { 5 };
@@ -55,18 +119,48 @@ unsafe fn send_native(event_type: &EventType, display: *mut xlib::Display) -> Op
                 let mut result: c_int = 1;
                 for _ in 0..delta_x.abs() {
                     result = result
-                        & xtest::XTestFakeButtonEvent(display, code_x, TRUE, 0)
-                        & xtest::XTestFakeButtonEvent(display, code_x, FALSE, 0)
+                        & fake_button(display, code_x, TRUE)
+                        & fake_button(display, code_x, FALSE)
                 }
                 for _ in 0..delta_y.abs() {
                     result = result
-                        & xtest::XTestFakeButtonEvent(display, code_y, TRUE, 0)
-                        & xtest::XTestFakeButtonEvent(display, code_y, FALSE, 0)
+                        & fake_button(display, code_y, TRUE)
+                        & fake_button(display, code_y, FALSE)
                 }
                 result
             }
+            EventType::Swipe { .. }
+            | EventType::Pinch { .. }
+            | EventType::Hold { .. }
+            | EventType::TouchDown { .. }
+            | EventType::TouchMove { .. }
+            | EventType::TouchUp { .. }
+            | EventType::TouchCancel { .. }
+            | EventType::TouchFrame
+            | EventType::TabletProximity { .. }
+            | EventType::TabletTip { .. }
+            | EventType::TabletAxis { .. }
+            | EventType::TabletButton { .. }
+            | EventType::TabletPadButton { .. }
+            | EventType::Switch { .. } => {
+                return Err(SimulateError::UnsupportedEvent(*event_type));
+            }
+            EventType::Scroll {
+                delta_x, delta_y, ..
+            } => {
+                let (delta_x, delta_y) = SCROLL_REMAINDER
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .add(*delta_x, *delta_y);
+                let wheel = EventType::Wheel { delta_x, delta_y };
+                return send_native(&wheel, display);
+            }
         };
-        if res == 0 { None } else { Some(()) }
+        if res == 0 {
+            Err(SimulateError::Rejected)
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -74,19 +168,30 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     unsafe {
         let dpy = xlib::XOpenDisplay(null());
         if dpy.is_null() {
-            return Err(SimulateError);
+            return Err(SimulateError::Display(DisplayError::NoDisplay));
         }
-        match send_native(event_type, dpy) {
-            Some(_) => {
-                xlib::XFlush(dpy);
-                xlib::XSync(dpy, 0);
-                xlib::XCloseDisplay(dpy);
-                Ok(())
-            }
-            None => {
-                xlib::XCloseDisplay(dpy);
-                Err(SimulateError)
-            }
+        let result = send_native(event_type, dpy);
+        if result.is_ok() {
+            xlib::XFlush(dpy);
+            xlib::XSync(dpy, 0);
         }
+        xlib::XCloseDisplay(dpy);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_remainder() {
+        let mut remainder = ScrollRemainder::default();
+        assert_eq!(remainder.add(0.4, -0.5), (0, 0));
+        assert_eq!(remainder.add(0.4, -0.5), (0, -1));
+        assert_eq!(remainder.add(0.4, 2.5), (1, 2));
+        // A change of direction drops what was left the other way.
+        assert_eq!(remainder.add(-0.5, 0.0), (0, 0));
+        assert_eq!(remainder, ScrollRemainder { x: -0.5, y: 0.5 });
     }
 }
@@ -1,16 +1,34 @@
 #![allow(clippy::upper_case_acronyms)]
 use crate::macos::keyboard::Keyboard;
-use crate::rdev::{Button, Event, EventType};
+use crate::rdev::{Button, Event, EventSource, EventType, Modifiers, RawCode};
 use core::ptr::NonNull;
 use lazy_static::lazy_static;
-use objc2_core_graphics::{CGEvent, CGEventField, CGEventFlags, CGEventType};
+use objc2_core_graphics::{CGEvent, CGEventField, CGEventFlags, CGEventSourceStateID, CGEventType};
 use std::convert::TryInto;
 use std::sync::Mutex;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use crate::macos::keycodes::key_from_code;
+use crate::keycodes::macos::key_from_code;
 
-use super::keycodes::key_from_special_key;
+use crate::keycodes::macos::key_from_special_key;
+
+/// The flags already include the change of a `FlagsChanged` event.
+/// Both Option keys are `ALT`, there is no Num Lock.
+fn flags_to_modifiers(flags: CGEventFlags) -> Modifiers {
+    let masks = [
+        (CGEventFlags::MaskShift, Modifiers::SHIFT),
+        (CGEventFlags::MaskControl, Modifiers::CTRL),
+        (CGEventFlags::MaskAlternate, Modifiers::ALT),
+        (CGEventFlags::MaskCommand, Modifiers::META),
+        (CGEventFlags::MaskAlphaShift, Modifiers::CAPS_LOCK),
+    ];
+    masks
+        .into_iter()
+        .filter(|(mask, _)| flags.contains(*mask))
+        .fold(Modifiers::empty(), |modifiers, (_, modifier)| {
+            modifiers | modifier
+        })
+}
 
 lazy_static! {
     pub static ref LAST_FLAGS: Mutex<CGEventFlags> = Mutex::new(CGEventFlags(0));
@@ -21,6 +39,66 @@ pub fn set_is_main_thread(b: bool) {
     KEYBOARD_STATE.lock().unwrap().set_is_main_thread(b);
 }
 
+/// Stored in the source user data of the events `simulate` posts, to recognize them.
+pub const RDEV_USER_DATA: i64 = 0x7264_6576;
+
+unsafe fn get_source(cg_event: NonNull<CGEvent>) -> EventSource {
+    unsafe {
+        let user_data = CGEvent::integer_value_field(
+            Some(cg_event.as_ref()),
+            CGEventField::EventSourceUserData,
+        );
+        let state_id =
+            CGEvent::integer_value_field(Some(cg_event.as_ref()), CGEventField::EventSourceStateID);
+        if user_data == RDEV_USER_DATA {
+            EventSource::Rdev
+        } else if state_id != CGEventSourceStateID::HIDSystemState.0 as i64 {
+            EventSource::OtherSynthetic
+        } else {
+            EventSource::Hardware
+        }
+    }
+}
+
+/// Only called for key events, which come from keyboard or system defined events.
+unsafe fn get_raw_code(_type: CGEventType, cg_event: NonNull<CGEvent>) -> Option<RawCode> {
+    unsafe {
+        if _type == CGEventType(14) {
+            let data1 = CGEvent::integer_value_field(Some(cg_event.as_ref()), CGEventField(149));
+            return Some(RawCode::MacOSSpecialKey(
+                ((data1 & 0xffff0000) >> 16) as u32,
+            ));
+        }
+        let code = CGEvent::integer_value_field(
+            Some(cg_event.as_ref()),
+            CGEventField::KeyboardEventKeycode,
+        );
+        Some(RawCode::MacOS(code.try_into().ok()?))
+    }
+}
+
+/// Buttons other than left and right share the `OtherMouse` event types.
+fn get_other_button(cg_event: &CGEvent) -> Button {
+    let number = CGEvent::integer_value_field(Some(cg_event), CGEventField::MouseEventButtonNumber);
+    match number {
+        2 => Button::Middle,
+        3 => Button::Back,
+        4 => Button::Forward,
+        number => Button::Unknown(number as u8),
+    }
+}
+
+/// Inverse of `get_other_button`, `None` for left and right.
+pub fn other_button_number(button: &Button) -> Option<u32> {
+    match button {
+        Button::Left | Button::Right => None,
+        Button::Middle => Some(2),
+        Button::Back => Some(3),
+        Button::Forward => Some(4),
+        Button::Unknown(number) => Some((*number).into()),
+    }
+}
+
 pub unsafe fn convert(
     _type: CGEventType,
     cg_event: NonNull<CGEvent>,
@@ -32,6 +110,14 @@ pub unsafe fn convert(
             CGEventType::LeftMouseUp => Some(EventType::ButtonRelease(Button::Left)),
             CGEventType::RightMouseDown => Some(EventType::ButtonPress(Button::Right)),
             CGEventType::RightMouseUp => Some(EventType::ButtonRelease(Button::Right)),
+            CGEventType::OtherMouseDown => {
+                let button = get_other_button(cg_event.as_ref());
+                Some(EventType::ButtonPress(button))
+            }
+            CGEventType::OtherMouseUp => {
+                let button = get_other_button(cg_event.as_ref());
+                Some(EventType::ButtonRelease(button))
+            }
             CGEventType::MouseMoved => {
                 let point = CGEvent::location(Some(cg_event.as_ref()));
                 // let point = cg_event.location();
@@ -47,7 +133,7 @@ pub unsafe fn convert(
                     y: point.y,
                 })
             }
-            CGEventType::RightMouseDragged => {
+            CGEventType::RightMouseDragged | CGEventType::OtherMouseDragged => {
                 let point = CGEvent::location(Some(cg_event.as_ref()));
                 Some(EventType::MouseMove {
                     x: point.x,
@@ -61,7 +147,15 @@ pub unsafe fn convert(
                     CGEventField::KeyboardEventKeycode,
                 );
                 let key = key_from_code(code.try_into().ok()?);
-                Some(EventType::KeyPress(key))
+                let repeat = CGEvent::integer_value_field(
+                    Some(cg_event.as_ref()),
+                    CGEventField::KeyboardEventAutorepeat,
+                );
+                if repeat != 0 {
+                    Some(EventType::KeyRepeat(key))
+                } else {
+                    Some(EventType::KeyPress(key))
+                }
             }
             CGEventType::KeyUp => {
                 let code = CGEvent::integer_value_field(
@@ -174,7 +268,7 @@ pub unsafe fn convert(
         };
         if let Some(event_type) = option_type {
             let name = match event_type {
-                EventType::KeyPress(_) => {
+                EventType::KeyPress(_) | EventType::KeyRepeat(_) => {
                     let code = CGEvent::integer_value_field(
                         Some(cg_event.as_ref()),
                         CGEventField::KeyboardEventKeycode,
@@ -184,10 +278,23 @@ pub unsafe fn convert(
                 }
                 _ => None,
             };
+            let raw_code = match event_type {
+                EventType::KeyPress(_) | EventType::KeyRelease(_) | EventType::KeyRepeat(_) => {
+                    get_raw_code(_type, cg_event)
+                }
+                _ => None,
+            };
             return Some(Event {
                 event_type,
                 time: SystemTime::now(),
+                timestamp: Some(Duration::from_nanos(CGEvent::timestamp(Some(
+                    cg_event.as_ref(),
+                )))),
                 name,
+                device: None,
+                source: get_source(cg_event),
+                modifiers: flags_to_modifiers(CGEvent::flags(Some(cg_event.as_ref()))),
+                raw_code,
             });
         }
     }
@@ -1,16 +1,20 @@
 #![allow(improper_ctypes_definitions)]
 use crate::macos::common::*;
-use crate::rdev::{Event, GrabError};
-use objc2_core_foundation::{CFMachPort, CFRunLoop, kCFRunLoopCommonModes};
+use crate::rdev::{Event, GrabError, GrabHandle};
+use objc2_core_foundation::{CFMachPort, CFRunLoop, kCFRunLoopCommonModes, kCFRunLoopDefaultMode};
 use objc2_core_graphics::{
     CGEvent, CGEventTapCallBack, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
     CGEventTapProxy, CGEventType, kCGEventMaskForAllEvents,
 };
 use objc2_foundation::NSAutoreleasePool;
 use std::os::raw::c_void;
-use std::ptr::{NonNull, null_mut};
+use std::ptr::NonNull;
 
-static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event) -> Option<Event>>> = None;
+/// The callback of the listening loop, passed to the tap as its `user_info`.
+type Callback = Box<dyn FnMut(Event) -> Option<Event>>;
+
+/// How long the run loop runs before checking the handle again, in seconds.
+const STOP_POLL_INTERVAL: f64 = 0.1;
 
 #[link(name = "Cocoa", kind = "framework")]
 unsafe extern "C" {}
@@ -19,19 +23,16 @@ unsafe extern "C-unwind" fn raw_callback(
     _proxy: CGEventTapProxy,
     _type: CGEventType,
     cg_event: NonNull<CGEvent>,
-    _user_info: *mut c_void,
+    user_info: *mut c_void,
 ) -> *mut CGEvent {
     let opt = KEYBOARD_STATE.lock();
     if let Ok(mut keyboard) = opt {
         unsafe {
             if let Some(event) = convert(_type, cg_event, &mut keyboard) {
-                // Reborrowing the global callback pointer.
-                // Totally UB. but not sure there's a great alternative.
-                let ptr = &raw mut GLOBAL_CALLBACK;
-                if let Some(callback) = &mut *ptr {
-                    if callback(event).is_none() {
-                        CGEvent::set_type(Some(cg_event.as_ref()), CGEventType::Null)
-                    }
+                // The tap only runs while the loop owning the callback does.
+                let callback = &mut *(user_info as *mut Callback);
+                if callback(event).is_none() {
+                    CGEvent::set_type(Some(cg_event.as_ref()), CGEventType::Null)
                 }
             }
         }
@@ -39,12 +40,12 @@ unsafe extern "C-unwind" fn raw_callback(
     cg_event.as_ptr()
 }
 
-pub fn grab<T>(callback: T) -> Result<(), GrabError>
+pub fn grab<T>(callback: T, handle: &GrabHandle) -> Result<(), GrabError>
 where
     T: FnMut(Event) -> Option<Event> + 'static,
 {
     unsafe {
-        GLOBAL_CALLBACK = Some(Box::new(callback));
+        let mut user_callback: Callback = Box::new(callback);
         let _pool = NSAutoreleasePool::new();
         let callback: CGEventTapCallBack = Some(raw_callback);
         let tap = CGEvent::tap_create(
@@ -53,7 +54,7 @@ where
             CGEventTapOptions::Default,
             kCGEventMaskForAllEvents.into(),
             callback,
-            null_mut(),
+            (&raw mut user_callback).cast(),
         )
         .ok_or(GrabError::EventTapError)?;
         let loop_ = CFMachPort::new_run_loop_source(None, Some(&tap), 0)
@@ -63,7 +64,11 @@ where
         current_loop.add_source(Some(&loop_), kCFRunLoopCommonModes);
 
         CGEvent::tap_enable(&tap, true);
-        CFRunLoop::run();
+        while !handle.is_stopped() {
+            CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, STOP_POLL_INTERVAL, false);
+        }
+        CGEvent::tap_enable(&tap, false);
+        current_loop.remove_source(Some(&loop_), kCFRunLoopCommonModes);
     }
     Ok(())
 }
@@ -1,5 +1,5 @@
 #![allow(clippy::upper_case_acronyms)]
-use crate::macos::keycodes::code_from_key;
+use crate::keycodes::macos::code_from_key;
 use crate::rdev::{EventType, Key, KeyboardState};
 use core_foundation::base::{CFRelease, OSStatus};
 use core_foundation::string::UniChar;
@@ -65,6 +65,7 @@ pub struct Keyboard {
     dead_state: u32,
     shift: bool,
     caps_lock: bool,
+    alt_gr: bool,
 }
 impl Keyboard {
     pub fn new() -> Option<Keyboard> {
@@ -73,6 +74,7 @@ impl Keyboard {
             dead_state: 0,
             shift: false,
             caps_lock: false,
+            alt_gr: false,
         })
     }
 
@@ -81,7 +83,12 @@ impl Keyboard {
     }
 
     fn modifier_state(&self) -> ModifierState {
-        if self.caps_lock || self.shift { 2 } else { 0 }
+        let mut state = if self.caps_lock || self.shift { 2 } else { 0 };
+        // AltGr is the right Option key.
+        if self.alt_gr {
+            state += 1 << 3;
+        }
+        state
     }
 
     pub(crate) unsafe fn create_string_for_key(
@@ -148,6 +155,9 @@ impl Keyboard {
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
+            // A repeat types the character again, Caps Lock doesn't toggle again.
+            EventType::KeyRepeat(Key::CapsLock) => None,
+            EventType::KeyRepeat(key) => self.add(&EventType::KeyPress(*key)),
             EventType::KeyPress(key) => match key {
                 Key::ShiftLeft | Key::ShiftRight => {
                     self.shift = true;
@@ -157,6 +167,10 @@ impl KeyboardState for Keyboard {
                     self.caps_lock = !self.caps_lock;
                     None
                 }
+                Key::AltGr => {
+                    self.alt_gr = true;
+                    None
+                }
                 key => {
                     let code = code_from_key(*key)?;
                     unsafe { self.string_from_code(code.into(), self.modifier_state()) }
@@ -166,6 +180,10 @@ impl KeyboardState for Keyboard {
                 self.shift = false;
                 None
             }
+            EventType::KeyRelease(Key::AltGr) => {
+                self.alt_gr = false;
+                None
+            }
             _ => None,
         }
     }
@@ -174,6 +192,7 @@ impl KeyboardState for Keyboard {
         self.dead_state = 0;
         self.shift = false;
         self.caps_lock = false;
+        self.alt_gr = false;
     }
 }
 
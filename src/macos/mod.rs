@@ -3,7 +3,6 @@ mod display;
 #[cfg(feature = "unstable_grab")]
 mod grab;
 mod keyboard;
-mod keycodes;
 mod listen;
 mod simulate;
 
@@ -8,20 +8,20 @@ use crate::Key;
 use crate::rdev::{Button, EventType, SimulateError};
 use std::convert::TryInto;
 
-use crate::macos::common::LAST_FLAGS;
-use crate::macos::keycodes::code_from_key;
+use crate::keycodes::macos::code_from_key;
+use crate::macos::common::{LAST_FLAGS, RDEV_USER_DATA, other_button_number};
 
 unsafe fn convert_native_with_source(
     event_type: &EventType,
     source: CFRetained<CGEventSource>,
-) -> Option<CFRetained<CGEvent>> {
+) -> Result<CFRetained<CGEvent>, SimulateError> {
     unsafe {
         match event_type {
             EventType::KeyPress(key) => {
-                let code = code_from_key(*key)?;
+                let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
                 // For modifier keys, we need to use FlagsChanged event type
                 if is_modifier_key(*key) {
-                    let event = CGEvent::new(Some(&source))?;
+                    let event = CGEvent::new(Some(&source)).ok_or(SimulateError::Rejected)?;
                     CGEvent::set_type(Some(&event), CGEventType::FlagsChanged);
                     CGEvent::set_integer_value_field(
                         Some(&event),
@@ -48,19 +48,20 @@ unsafe fn convert_native_with_source(
                     }
                     CGEvent::set_flags(Some(&event), *new_flags);
                     // event.set_flags(*new_flags);
-                    Some(event)
+                    Ok(event)
                 } else {
                     // For non-modifier keys, use regular key events
-                    let event = CGEvent::new_keyboard_event(Some(&source), code, true)?;
+                    let event = CGEvent::new_keyboard_event(Some(&source), code, true)
+                        .ok_or(SimulateError::Rejected)?;
                     CGEvent::set_flags(Some(&event), *LAST_FLAGS.lock().unwrap());
-                    Some(event)
+                    Ok(event)
                 }
             }
             EventType::KeyRelease(key) => {
-                let code = code_from_key(*key)?;
+                let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
                 // For modifier keys, we need to use FlagsChanged event type
                 if is_modifier_key(*key) {
-                    let event = CGEvent::new(Some(&source))?;
+                    let event = CGEvent::new(Some(&source)).ok_or(SimulateError::Rejected)?;
                     CGEvent::set_type(Some(&event), CGEventType::FlagsChanged);
                     CGEvent::set_integer_value_field(
                         Some(&event),
@@ -86,50 +87,96 @@ unsafe fn convert_native_with_source(
                         _ => {}
                     }
                     CGEvent::set_flags(Some(&event), *new_flags);
-                    Some(event)
+                    Ok(event)
                 } else {
                     // For non-modifier keys, use regular key events
-                    let event = CGEvent::new_keyboard_event(Some(&source), code, false)?;
+                    let event = CGEvent::new_keyboard_event(Some(&source), code, false)
+                        .ok_or(SimulateError::Rejected)?;
                     CGEvent::set_flags(Some(&event), *LAST_FLAGS.lock().unwrap());
-                    Some(event)
+                    Ok(event)
                 }
             }
+            // Modifiers don't repeat.
+            EventType::KeyRepeat(key) if is_modifier_key(*key) => {
+                Err(SimulateError::UnsupportedEvent(*event_type))
+            }
+            EventType::KeyRepeat(key) => {
+                let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
+                let event = CGEvent::new_keyboard_event(Some(&source), code, true)
+                    .ok_or(SimulateError::Rejected)?;
+                CGEvent::set_integer_value_field(
+                    Some(&event),
+                    CGEventField::KeyboardEventAutorepeat,
+                    1,
+                );
+                CGEvent::set_flags(Some(&event), *LAST_FLAGS.lock().unwrap());
+                Ok(event)
+            }
             EventType::ButtonPress(button) => {
                 let point = get_current_mouse_location()?;
-                let event = match button {
-                    Button::Left => CGEventType::LeftMouseDown,
-                    Button::Right => CGEventType::RightMouseDown,
-                    _ => return None,
+                let (event, number) = match button {
+                    Button::Left => (CGEventType::LeftMouseDown, 0),
+                    Button::Right => (CGEventType::RightMouseDown, 1),
+                    _ => (
+                        CGEventType::OtherMouseDown,
+                        other_button_number(button)
+                            .ok_or(SimulateError::UnmappedButton(*button))?,
+                    ),
                 };
-                CGEvent::new_mouse_event(
-                    Some(&source),
-                    event,
-                    point,
-                    CGMouseButton::Left, // ignored because we don't use OtherMouse EventType
-                )
+                // The button is only looked at for OtherMouse events.
+                CGEvent::new_mouse_event(Some(&source), event, point, CGMouseButton(number))
+                    .ok_or(SimulateError::Rejected)
             }
             EventType::ButtonRelease(button) => {
                 let point = get_current_mouse_location()?;
-                let event = match button {
-                    Button::Left => CGEventType::LeftMouseUp,
-                    Button::Right => CGEventType::RightMouseUp,
-                    _ => return None,
+                let (event, number) = match button {
+                    Button::Left => (CGEventType::LeftMouseUp, 0),
+                    Button::Right => (CGEventType::RightMouseUp, 1),
+                    _ => (
+                        CGEventType::OtherMouseUp,
+                        other_button_number(button)
+                            .ok_or(SimulateError::UnmappedButton(*button))?,
+                    ),
                 };
+                // The button is only looked at for OtherMouse events.
+                CGEvent::new_mouse_event(Some(&source), event, point, CGMouseButton(number))
+                    .ok_or(SimulateError::Rejected)
+            }
+            EventType::MouseMove { x, y } => {
+                let point = CGPoint { x: (*x), y: (*y) };
                 CGEvent::new_mouse_event(
                     Some(&source),
-                    event,
+                    CGEventType::MouseMoved,
                     point,
-                    CGMouseButton::Left, // ignored because we don't use OtherMouse EventType
+                    CGMouseButton::Left,
                 )
+                .ok_or(SimulateError::Rejected)
             }
-            EventType::MouseMove { x, y } => {
-                let point = CGPoint { x: (*x), y: (*y) };
-                CGEvent::new_mouse_event(
+            EventType::MouseMoveRelative { dx, dy } => {
+                let current = get_current_mouse_location()?;
+                let point = CGPoint {
+                    x: current.x + dx,
+                    y: current.y + dy,
+                };
+                let event = CGEvent::new_mouse_event(
                     Some(&source),
                     CGEventType::MouseMoved,
                     point,
                     CGMouseButton::Left,
                 )
+                .ok_or(SimulateError::Rejected)?;
+                // Games reading deltas instead of positions look at these.
+                CGEvent::set_integer_value_field(
+                    Some(&event),
+                    CGEventField::MouseEventDeltaX,
+                    dx.round() as i64,
+                );
+                CGEvent::set_integer_value_field(
+                    Some(&event),
+                    CGEventField::MouseEventDeltaY,
+                    dy.round() as i64,
+                );
+                Ok(event)
             }
             EventType::Wheel { delta_x, delta_y } => {
                 let wheel_count = 2;
@@ -137,27 +184,65 @@ unsafe fn convert_native_with_source(
                     Some(&source),
                     CGScrollEventUnit::Pixel,
                     wheel_count,
-                    (*delta_y).try_into().ok()?,
-                    (*delta_x).try_into().ok()?,
+                    (*delta_y)
+                        .try_into()
+                        .map_err(|_| SimulateError::OutOfRange)?,
+                    (*delta_x)
+                        .try_into()
+                        .map_err(|_| SimulateError::OutOfRange)?,
+                    0,
+                )
+                .ok_or(SimulateError::Rejected)
+            }
+            EventType::Swipe { .. }
+            | EventType::Pinch { .. }
+            | EventType::Hold { .. }
+            | EventType::TouchDown { .. }
+            | EventType::TouchMove { .. }
+            | EventType::TouchUp { .. }
+            | EventType::TouchCancel { .. }
+            | EventType::TouchFrame
+            | EventType::TabletProximity { .. }
+            | EventType::TabletTip { .. }
+            | EventType::TabletAxis { .. }
+            | EventType::TabletButton { .. }
+            | EventType::TabletPadButton { .. }
+            | EventType::Switch { .. } => Err(SimulateError::UnsupportedEvent(*event_type)),
+            EventType::Scroll {
+                delta_x, delta_y, ..
+            } => {
+                let wheel_count = 2;
+                CGEvent::new_scroll_wheel_event2(
+                    Some(&source),
+                    CGScrollEventUnit::Pixel,
+                    wheel_count,
+                    (delta_y * PIXELS_PER_NOTCH).round() as i32,
+                    (delta_x * PIXELS_PER_NOTCH).round() as i32,
                     0,
                 )
+                .ok_or(SimulateError::Rejected)
             }
         }
     }
 }
 
-unsafe fn convert_native(event_type: &EventType) -> Option<CFRetained<CGEvent>> {
+/// Roughly what a wheel click scrolls, MacOS lines are about 10 pixels.
+const PIXELS_PER_NOTCH: f64 = 10.0;
+
+unsafe fn convert_native(event_type: &EventType) -> Result<CFRetained<CGEvent>, SimulateError> {
     unsafe {
-        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)?;
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .ok_or(SimulateError::Rejected)?;
         convert_native_with_source(event_type, source)
     }
 }
 
-unsafe fn get_current_mouse_location() -> Option<CGPoint> {
+unsafe fn get_current_mouse_location() -> Result<CGPoint, SimulateError> {
     unsafe {
-        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)?;
-        let event = CGEvent::new(Some(&source))?;
-        Some(CGEvent::location(Some(&event)))
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .ok_or(SimulateError::Rejected)?;
+        let event = CGEvent::new(Some(&source)).ok_or(SimulateError::Rejected)?;
+        Ok(CGEvent::location(Some(&event)))
     }
 }
 
@@ -180,11 +265,13 @@ unsafe extern "C" {}
 
 pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     unsafe {
-        if let Some(cg_event) = convert_native(event_type) {
-            CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&cg_event));
-            Ok(())
-        } else {
-            Err(SimulateError)
-        }
+        let cg_event = convert_native(event_type)?;
+        CGEvent::set_integer_value_field(
+            Some(&cg_event),
+            CGEventField::EventSourceUserData,
+            RDEV_USER_DATA,
+        );
+        CGEvent::post(CGEventTapLocation::HIDEventTap, Some(&cg_event));
+        Ok(())
     }
 }
@@ -0,0 +1,593 @@
+use crate::rdev::{
+    Button, EventType, GesturePhase, Key, ParseNameError, ScrollSource, SwitchKind, TabletAxes,
+    ToolType,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// Canonical names are the variant names, unknown codes are written `Unknown(code)`.
+/// Parsing ignores the case of names, then tries the `aliases` function if any.
+macro_rules! decl_names {
+    (
+        $type:ident,
+        [$($variant:ident),* $(,)?]
+        $(, Unknown($code:ty))?
+        $(, aliases($aliases:path))?
+    ) => {
+        impl $type {
+            #[cfg_attr(not(test), allow(dead_code))]
+            pub(crate) const VARIANTS: &[$type] = &[$($type::$variant),*];
+        }
+
+        impl fmt::Display for $type {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    $(
+                        $type::$variant => f.write_str(stringify!($variant)),
+                    )*
+                    $(
+                        $type::Unknown(code) => {
+                            let code: &$code = code;
+                            write!(f, "Unknown({code})")
+                        }
+                    )?
+                }
+            }
+        }
+
+        impl FromStr for $type {
+            type Err = ParseNameError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(
+                    if s.eq_ignore_ascii_case(stringify!($variant)) {
+                        return Ok($type::$variant);
+                    }
+                )*
+                $(
+                    if let Some(code) = unknown_code::<$code>(s) {
+                        return Ok($type::Unknown(code));
+                    }
+                )?
+                $(
+                    if let Some(value) = $aliases(s) {
+                        return Ok(value);
+                    }
+                )?
+                Err(ParseNameError::new(s))
+            }
+        }
+    };
+}
+
+fn unknown_code<T: FromStr>(s: &str) -> Option<T> {
+    let (name, args) = split_call(s).ok()?;
+    match args.as_slice() {
+        [code] if name.eq_ignore_ascii_case("Unknown") => code.parse().ok(),
+        _ => None,
+    }
+}
+
+#[rustfmt::skip]
+decl_names!(Key, [
+    Alt, AltGr, Backspace, CapsLock, ControlLeft, ControlRight, Delete, DownArrow, End, Escape,
+    F1, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F2, F3, F4,
+    F5, F6, F7, F8, F9, Home, LeftArrow, MetaLeft, MetaRight, PageDown, PageUp, Return,
+    RightArrow, ShiftLeft, ShiftRight, Space, Tab, UpArrow, PrintScreen, ScrollLock, Pause,
+    NumLock, BackQuote, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0, Minus,
+    Equal, KeyQ, KeyW, KeyE, KeyR, KeyT, KeyY, KeyU, KeyI, KeyO, KeyP, LeftBracket,
+    RightBracket, KeyA, KeyS, KeyD, KeyF, KeyG, KeyH, KeyJ, KeyK, KeyL, SemiColon, Quote,
+    BackSlash, IntlBackslash, KeyZ, KeyX, KeyC, KeyV, KeyB, KeyN, KeyM, Comma, Dot, Slash,
+    Insert, KpReturn, KpMinus, KpPlus, KpMultiply, KpDivide, Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6,
+    Kp7, Kp8, Kp9, KpDelete, Function, VolumeUp, VolumeDown, VolumeMute, BrightnessUp,
+    BrightnessDown, PreviousTrack, PlayPause, PlayCd, NextTrack, ContextMenu, Power, Sleep,
+    Wake, Calculator, Mail, BrowserBack, BrowserForward, BrowserRefresh, BrowserStop,
+    BrowserSearch, BrowserFavorites, BrowserHome, Stop, Eject, Ro, Yen, Henkan, Muhenkan,
+    Hangul, Hanja, Help, Undo, Copy, Paste, Cut
+], Unknown(u32), aliases(Key::from_alias));
+
+decl_names!(Button, [Left, Right, Middle, Back, Forward], Unknown(u8));
+decl_names!(ScrollSource, [Wheel, Finger, Continuous]);
+decl_names!(GesturePhase, [Begin, Update, End, Cancel]);
+decl_names!(
+    ToolType,
+    [
+        Pen, Eraser, Brush, Pencil, Airbrush, Mouse, Lens, Totem, Unknown
+    ]
+);
+decl_names!(SwitchKind, [Lid, TabletMode], Unknown(u16));
+
+impl Key {
+    /// Common names of keys, checked after the canonical names.
+    /// Single letters and digits are the letter and digit keys.
+    fn from_alias(alias: &str) -> Option<Key> {
+        let alias = alias.to_ascii_lowercase();
+        let key = match alias.as_str() {
+            "ctrl" | "control" => Key::ControlLeft,
+            "shift" => Key::ShiftLeft,
+            "option" | "opt" => Key::Alt,
+            "cmd" | "command" | "win" | "windows" | "super" | "meta" => Key::MetaLeft,
+            "esc" => Key::Escape,
+            "enter" => Key::Return,
+            "kpenter" => Key::KpReturn,
+            "del" => Key::Delete,
+            "ins" => Key::Insert,
+            "pgup" => Key::PageUp,
+            "pgdn" => Key::PageDown,
+            "up" => Key::UpArrow,
+            "down" => Key::DownArrow,
+            "left" => Key::LeftArrow,
+            "right" => Key::RightArrow,
+            "menu" => Key::ContextMenu,
+            "grave" | "backtick" => Key::BackQuote,
+            "period" => Key::Dot,
+            "fn" => Key::Function,
+            alias => {
+                let mut chars = alias.chars();
+                return match (chars.next(), chars.next()) {
+                    (Some(c @ 'a'..='z'), None) => format!("Key{c}").parse().ok(),
+                    (Some(c @ '0'..='9'), None) => format!("Num{c}").parse().ok(),
+                    _ => None,
+                };
+            }
+        };
+        Some(key)
+    }
+
+    /// Parses a canonical name or a common alias like `ctrl`, `cmd`, `win` or `esc`,
+    /// same as `str::parse`.
+    /// Aliases only parse, the canonical name is what `Display` writes.
+    ///
+    /// ```
+    /// use rdev::Key;
+    ///
+    /// assert_eq!("ControlLeft".parse(), Ok(Key::ControlLeft));
+    /// assert_eq!("ctrl".parse(), Ok(Key::ControlLeft));
+    /// assert_eq!(Key::from_name("ctrl"), Ok(Key::ControlLeft));
+    /// assert_eq!(Key::ControlLeft.to_string(), "ControlLeft");
+    /// assert_eq!(Key::from_name("a"), Ok(Key::KeyA));
+    /// assert_eq!(Key::Unknown(42).to_string(), "Unknown(42)");
+    /// ```
+    pub fn from_name(name: &str) -> Result<Key, ParseNameError> {
+        name.parse()
+    }
+}
+
+/// Splits `Name(arg, arg)` into the name and its arguments, nested calls like
+/// `KeyPress(Unknown(42))` are kept whole. A bare `Name` has no arguments.
+fn split_call(s: &str) -> Result<(&str, Vec<&str>), ParseNameError> {
+    let s = s.trim();
+    let Some(open) = s.find('(') else {
+        return Ok((s, vec![]));
+    };
+    let inner = s[open + 1..]
+        .strip_suffix(')')
+        .ok_or_else(|| ParseNameError::new(s))?;
+    let mut args = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(ParseNameError::new(s)),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(ParseNameError::new(s));
+    }
+    if !inner.trim().is_empty() {
+        args.push(inner[start..].trim());
+    }
+    Ok((s[..open].trim(), args))
+}
+
+/// Writes `Name(arg, arg)`, or `Name` without arguments.
+fn write_call(f: &mut fmt::Formatter, name: &str, args: &[&dyn fmt::Display]) -> fmt::Result {
+    f.write_str(name)?;
+    if let Some((first, rest)) = args.split_first() {
+        write!(f, "({first}")?;
+        for arg in rest {
+            write!(f, ", {arg}")?;
+        }
+        f.write_str(")")?;
+    }
+    Ok(())
+}
+
+/// Arguments of an event being parsed.
+struct Args<'a> {
+    event: &'a str,
+    args: std::vec::IntoIter<&'a str>,
+}
+
+impl Args<'_> {
+    fn next<T: FromStr>(&mut self) -> Result<T, ParseNameError> {
+        self.args
+            .next()
+            .and_then(|arg| arg.parse().ok())
+            .ok_or_else(|| ParseNameError::new(self.event))
+    }
+
+    /// Keys also parse from their aliases, e.g. `KeyPress(ctrl)`.
+    fn key(&mut self) -> Result<Key, ParseNameError> {
+        self.args
+            .next()
+            .and_then(|arg| Key::from_name(arg).ok())
+            .ok_or_else(|| ParseNameError::new(self.event))
+    }
+
+    fn axes(&mut self) -> Result<TabletAxes, ParseNameError> {
+        Ok(TabletAxes {
+            x: self.next()?,
+            y: self.next()?,
+            pressure: self.next()?,
+            tilt_x: self.next()?,
+            tilt_y: self.next()?,
+        })
+    }
+}
+
+/// Events are written like the variants with their fields in order,
+/// e.g. `KeyPress(ControlLeft)`, `MouseMove(10, 20.5)` or `TouchFrame`.
+/// The tablet axes are flattened: `TabletAxis(Pen, x, y, pressure, tilt_x, tilt_y)`.
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventType::KeyPress(key) => write_call(f, "KeyPress", &[key]),
+            EventType::KeyRelease(key) => write_call(f, "KeyRelease", &[key]),
+            EventType::KeyRepeat(key) => write_call(f, "KeyRepeat", &[key]),
+            EventType::ButtonPress(button) => write_call(f, "ButtonPress", &[button]),
+            EventType::ButtonRelease(button) => write_call(f, "ButtonRelease", &[button]),
+            EventType::MouseMove { x, y } => write_call(f, "MouseMove", &[x, y]),
+            EventType::MouseMoveRelative { dx, dy } => {
+                write_call(f, "MouseMoveRelative", &[dx, dy])
+            }
+            EventType::Wheel { delta_x, delta_y } => write_call(f, "Wheel", &[delta_x, delta_y]),
+            EventType::Scroll {
+                delta_x,
+                delta_y,
+                source,
+            } => write_call(f, "Scroll", &[delta_x, delta_y, source]),
+            EventType::Swipe {
+                phase,
+                fingers,
+                dx,
+                dy,
+            } => write_call(f, "Swipe", &[phase, fingers, dx, dy]),
+            EventType::Pinch {
+                phase,
+                fingers,
+                dx,
+                dy,
+                scale,
+                rotation,
+            } => write_call(f, "Pinch", &[phase, fingers, dx, dy, scale, rotation]),
+            EventType::Hold { phase, fingers } => write_call(f, "Hold", &[phase, fingers]),
+            EventType::TouchDown { id, x, y } => write_call(f, "TouchDown", &[id, x, y]),
+            EventType::TouchMove { id, x, y } => write_call(f, "TouchMove", &[id, x, y]),
+            EventType::TouchUp { id } => write_call(f, "TouchUp", &[id]),
+            EventType::TouchCancel { id } => write_call(f, "TouchCancel", &[id]),
+            EventType::TouchFrame => write_call(f, "TouchFrame", &[]),
+            EventType::TabletProximity {
+                tool,
+                in_proximity,
+                axes,
+            } => write_call(
+                f,
+                "TabletProximity",
+                &[
+                    tool,
+                    in_proximity,
+                    &axes.x,
+                    &axes.y,
+                    &axes.pressure,
+                    &axes.tilt_x,
+                    &axes.tilt_y,
+                ],
+            ),
+            EventType::TabletTip { tool, down, axes } => write_call(
+                f,
+                "TabletTip",
+                &[
+                    tool,
+                    down,
+                    &axes.x,
+                    &axes.y,
+                    &axes.pressure,
+                    &axes.tilt_x,
+                    &axes.tilt_y,
+                ],
+            ),
+            EventType::TabletAxis { tool, axes } => write_call(
+                f,
+                "TabletAxis",
+                &[
+                    tool,
+                    &axes.x,
+                    &axes.y,
+                    &axes.pressure,
+                    &axes.tilt_x,
+                    &axes.tilt_y,
+                ],
+            ),
+            EventType::TabletButton {
+                tool,
+                button,
+                pressed,
+            } => write_call(f, "TabletButton", &[tool, button, pressed]),
+            EventType::TabletPadButton { button, pressed } => {
+                write_call(f, "TabletPadButton", &[button, pressed])
+            }
+            EventType::Switch { kind, on } => write_call(f, "Switch", &[kind, on]),
+        }
+    }
+}
+
+impl FromStr for EventType {
+    type Err = ParseNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = split_call(s)?;
+        let count = args.len();
+        let mut args = Args {
+            event: s,
+            args: args.into_iter(),
+        };
+        let event_type = match name.to_ascii_lowercase().as_str() {
+            "keypress" => EventType::KeyPress(args.key()?),
+            "keyrelease" => EventType::KeyRelease(args.key()?),
+            "keyrepeat" => EventType::KeyRepeat(args.key()?),
+            "buttonpress" => EventType::ButtonPress(args.next()?),
+            "buttonrelease" => EventType::ButtonRelease(args.next()?),
+            "mousemove" => EventType::MouseMove {
+                x: args.next()?,
+                y: args.next()?,
+            },
+            "mousemoverelative" => EventType::MouseMoveRelative {
+                dx: args.next()?,
+                dy: args.next()?,
+            },
+            "wheel" => EventType::Wheel {
+                delta_x: args.next()?,
+                delta_y: args.next()?,
+            },
+            "scroll" => EventType::Scroll {
+                delta_x: args.next()?,
+                delta_y: args.next()?,
+                source: args.next()?,
+            },
+            "swipe" => EventType::Swipe {
+                phase: args.next()?,
+                fingers: args.next()?,
+                dx: args.next()?,
+                dy: args.next()?,
+            },
+            "pinch" => EventType::Pinch {
+                phase: args.next()?,
+                fingers: args.next()?,
+                dx: args.next()?,
+                dy: args.next()?,
+                scale: args.next()?,
+                rotation: args.next()?,
+            },
+            "hold" => EventType::Hold {
+                phase: args.next()?,
+                fingers: args.next()?,
+            },
+            "touchdown" => EventType::TouchDown {
+                id: args.next()?,
+                x: args.next()?,
+                y: args.next()?,
+            },
+            "touchmove" => EventType::TouchMove {
+                id: args.next()?,
+                x: args.next()?,
+                y: args.next()?,
+            },
+            "touchup" => EventType::TouchUp { id: args.next()? },
+            "touchcancel" => EventType::TouchCancel { id: args.next()? },
+            "touchframe" => EventType::TouchFrame,
+            "tabletproximity" => EventType::TabletProximity {
+                tool: args.next()?,
+                in_proximity: args.next()?,
+                axes: args.axes()?,
+            },
+            "tablettip" => EventType::TabletTip {
+                tool: args.next()?,
+                down: args.next()?,
+                axes: args.axes()?,
+            },
+            "tabletaxis" => EventType::TabletAxis {
+                tool: args.next()?,
+                axes: args.axes()?,
+            },
+            "tabletbutton" => EventType::TabletButton {
+                tool: args.next()?,
+                button: args.next()?,
+                pressed: args.next()?,
+            },
+            "tabletpadbutton" => EventType::TabletPadButton {
+                button: args.next()?,
+                pressed: args.next()?,
+            },
+            "switch" => EventType::Switch {
+                kind: args.next()?,
+                on: args.next()?,
+            },
+            _ => return Err(ParseNameError::new(s)),
+        };
+        // Extra arguments are a mistake, not something to ignore.
+        if args.args.next().is_some() || count == 0 && s.contains('(') {
+            return Err(ParseNameError::new(s));
+        }
+        Ok(event_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_names() {
+        for key in Key::VARIANTS.iter().copied().chain([Key::Unknown(42)]) {
+            assert_eq!(key.to_string().parse(), Ok(key));
+            assert_eq!(key.to_string(), format!("{key:?}"));
+        }
+        assert_eq!("controlleft".parse(), Ok(Key::ControlLeft));
+        assert_eq!(Key::from_name("Ctrl"), Ok(Key::ControlLeft));
+        assert_eq!(Key::from_name("cmd"), Ok(Key::MetaLeft));
+        assert_eq!(Key::from_name("win"), Ok(Key::MetaLeft));
+        assert_eq!(Key::from_name("esc"), Ok(Key::Escape));
+        assert_eq!(Key::from_name("KpEnter"), Ok(Key::KpReturn));
+        assert_eq!(Key::from_name("q"), Ok(Key::KeyQ));
+        assert_eq!(Key::from_name("7"), Ok(Key::Num7));
+        assert_eq!("ctrl".parse(), Ok(Key::ControlLeft));
+        assert_eq!("Esc".parse(), Ok(Key::Escape));
+        assert!("controlle".parse::<Key>().is_err());
+        assert!(Key::from_name("Unknown(-1)").is_err());
+    }
+
+    #[test]
+    fn test_button_names() {
+        for button in Button::VARIANTS
+            .iter()
+            .copied()
+            .chain([Button::Unknown(12)])
+        {
+            assert_eq!(button.to_string().parse(), Ok(button));
+        }
+        assert!("Unknown(256)".parse::<Button>().is_err());
+    }
+
+    #[test]
+    fn test_field_names() {
+        for source in ScrollSource::VARIANTS {
+            assert_eq!(source.to_string().parse(), Ok(*source));
+        }
+        for phase in GesturePhase::VARIANTS {
+            assert_eq!(phase.to_string().parse(), Ok(*phase));
+        }
+        for tool in ToolType::VARIANTS {
+            assert_eq!(tool.to_string().parse(), Ok(*tool));
+        }
+        for kind in SwitchKind::VARIANTS
+            .iter()
+            .copied()
+            .chain([SwitchKind::Unknown(2)])
+        {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+    }
+
+    #[test]
+    fn test_event_type_names() {
+        let axes = TabletAxes {
+            x: 1.5,
+            y: 2.0,
+            pressure: 0.25,
+            tilt_x: -10.0,
+            tilt_y: 5.0,
+        };
+        let event_types = [
+            EventType::KeyPress(Key::Unknown(42)),
+            EventType::KeyRelease(Key::ControlLeft),
+            EventType::KeyRepeat(Key::KeyA),
+            EventType::ButtonPress(Button::Back),
+            EventType::ButtonRelease(Button::Unknown(12)),
+            EventType::MouseMove { x: 10.0, y: 20.5 },
+            EventType::MouseMoveRelative { dx: -1.0, dy: 0.5 },
+            EventType::Wheel {
+                delta_x: 0,
+                delta_y: -1,
+            },
+            EventType::Scroll {
+                delta_x: 0.25,
+                delta_y: 1e-3,
+                source: ScrollSource::Finger,
+            },
+            EventType::Swipe {
+                phase: GesturePhase::Update,
+                fingers: 3,
+                dx: 1.0,
+                dy: -2.0,
+            },
+            EventType::Pinch {
+                phase: GesturePhase::Begin,
+                fingers: 2,
+                dx: 0.0,
+                dy: 0.0,
+                scale: 1.1,
+                rotation: -3.0,
+            },
+            EventType::Hold {
+                phase: GesturePhase::Cancel,
+                fingers: 4,
+            },
+            EventType::TouchDown {
+                id: 1,
+                x: 3.0,
+                y: 4.0,
+            },
+            EventType::TouchMove {
+                id: 1,
+                x: 5.0,
+                y: 6.0,
+            },
+            EventType::TouchUp { id: 1 },
+            EventType::TouchCancel { id: 2 },
+            EventType::TouchFrame,
+            EventType::TabletProximity {
+                tool: ToolType::Pen,
+                in_proximity: true,
+                axes,
+            },
+            EventType::TabletTip {
+                tool: ToolType::Eraser,
+                down: false,
+                axes,
+            },
+            EventType::TabletAxis {
+                tool: ToolType::Unknown,
+                axes,
+            },
+            EventType::TabletButton {
+                tool: ToolType::Pen,
+                button: 331,
+                pressed: true,
+            },
+            EventType::TabletPadButton {
+                button: 0,
+                pressed: false,
+            },
+            EventType::Switch {
+                kind: SwitchKind::Unknown(2),
+                on: true,
+            },
+        ];
+        for event_type in event_types {
+            assert_eq!(event_type.to_string().parse(), Ok(event_type));
+        }
+        assert_eq!(
+            EventType::MouseMove { x: 10.0, y: 20.5 }.to_string(),
+            "MouseMove(10, 20.5)"
+        );
+        assert_eq!(
+            "keypress( ctrl )".parse(),
+            Ok(EventType::KeyPress(Key::ControlLeft))
+        );
+        for invalid in [
+            "KeyPress",
+            "KeyPress(A, B)",
+            "TouchFrame()",
+            "MouseMove(1, 2",
+        ] {
+            assert!(invalid.parse::<EventType>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use bitflags::bitflags;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use std::{fmt, fmt::Display};
 
 // /// Callback type to send to listen function.
@@ -9,6 +13,94 @@ use std::{fmt, fmt::Display};
 /// Callback type to send to grab function.
 pub type GrabCallback = fn(event: Event) -> Option<Event>;
 
+type Waker = Box<dyn Fn() + Send>;
+
+#[derive(Default)]
+struct StopState {
+    stopped: AtomicBool,
+    /// One waker per running loop, a handle can be shared by several loops.
+    wakers: Mutex<Vec<(u64, Waker)>>,
+    next_token: AtomicU64,
+}
+
+/// Handle used to stop a running `listen_until` (or `grab_until`) loop from
+/// another thread. The handle is cheap to clone, every clone controls the same loop.
+/// Once stopped, a handle stays stopped, create a new one to listen again.
+///
+/// ```no_run
+/// use rdev::{listen_until, ListenHandle};
+/// use std::thread;
+///
+/// let handle = ListenHandle::new();
+/// let stopper = handle.clone();
+/// thread::spawn(move || {
+///     thread::sleep(std::time::Duration::from_secs(5));
+///     stopper.stop();
+/// });
+/// // This will block for 5 seconds.
+/// listen_until(|event| println!("{:?}", event), &handle).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct ListenHandle {
+    state: Arc<StopState>,
+}
+
+/// Same as [`ListenHandle`], used by `grab_until`.
+pub type GrabHandle = ListenHandle;
+
+impl ListenHandle {
+    pub fn new() -> ListenHandle {
+        ListenHandle::default()
+    }
+
+    /// Asks the loop to stop. The blocking `listen_until`/`grab_until` call
+    /// returns `Ok(())` shortly after, once devices are released.
+    pub fn stop(&self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        if let Ok(wakers) = self.state.wakers.lock() {
+            for (_, waker) in wakers.iter() {
+                waker();
+            }
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Backends blocking on something else than a timeout register here
+    /// how to wake themselves up. They need to check `is_stopped` *after*
+    /// registering, so that a `stop` happening in between is not lost.
+    /// Returns the token to give to `remove_waker` once the loop is over.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) fn add_waker<F>(&self, waker: F) -> u64
+    where
+        F: Fn() + Send + 'static,
+    {
+        let token = self.state.next_token.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut wakers) = self.state.wakers.lock() {
+            wakers.push((token, Box::new(waker)));
+        }
+        token
+    }
+
+    /// Removes the waker of one loop, the other loops sharing the handle keep theirs.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) fn remove_waker(&self, token: u64) {
+        if let Ok(mut wakers) = self.state.wakers.lock() {
+            wakers.retain(|(waker_token, _)| *waker_token != token);
+        }
+    }
+}
+
+impl fmt::Debug for ListenHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ListenHandle")
+            .field("stopped", &self.is_stopped())
+            .finish()
+    }
+}
+
 /// Errors that occur when trying to capture OS events.
 /// Be careful on Mac, not setting accessibility does not cause an error
 /// it justs ignores events.
@@ -33,6 +125,49 @@ pub enum ListenError {
     KeyHookError(u32),
     /// Windows
     MouseHookError(u32),
+    /// Linux
+    IoError(std::io::Error),
+    /// Linux (Wayland), libinput could not use the seat, e.g. without udev.
+    SeatError(String),
+    /// Linux, no input device could be opened because access to this one was refused.
+    /// Reading `/dev/input` needs root or being in the `input` group.
+    PermissionDenied(PathBuf),
+}
+
+impl Display for ListenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenError::EventTapError => write!(f, "Could not create the event tap"),
+            ListenError::LoopSourceError => write!(f, "Could not create the run loop source"),
+            ListenError::MissingDisplayError => write!(f, "Could not open the X display"),
+            ListenError::KeyboardError => write!(f, "Could not load the keyboard layout"),
+            ListenError::RecordContextEnablingError => {
+                write!(f, "Could not enable the XRecord context")
+            }
+            ListenError::RecordContextError => write!(f, "Could not create the XRecord context"),
+            ListenError::XRecordExtensionError => {
+                write!(f, "The X server has no XRecord extension")
+            }
+            ListenError::KeyHookError(code) => {
+                write!(f, "Could not set the keyboard hook (error {})", code)
+            }
+            ListenError::MouseHookError(code) => {
+                write!(f, "Could not set the mouse hook (error {})", code)
+            }
+            ListenError::IoError(err) => write!(f, "{}", err),
+            ListenError::SeatError(seat) => write!(f, "Could not assign the seat `{}`", seat),
+            ListenError::PermissionDenied(path) => permission_denied(f, path),
+        }
+    }
+}
+
+impl std::error::Error for ListenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ListenError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 /// Errors that occur when trying to grab OS events.
@@ -54,20 +189,75 @@ pub enum GrabError {
     /// Windows
     MouseHookError(u32),
     /// All
-    SimulateError,
+    SimulateError(SimulateError),
     IoError(std::io::Error),
+    /// Linux, access to an input device (or to `/dev/uinput`) was refused.
+    /// Grabbing needs root or being in the `input` group.
+    PermissionDenied(PathBuf),
+    /// Linux, `/dev/uinput` is missing, the `uinput` kernel module is not loaded.
+    UinputUnavailable,
+}
+
+impl Display for GrabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrabError::EventTapError => write!(f, "Could not create the event tap"),
+            GrabError::LoopSourceError => write!(f, "Could not create the run loop source"),
+            GrabError::MissingDisplayError => write!(f, "Could not get the display size"),
+            GrabError::KeyboardError => write!(f, "Could not load the keyboard layout"),
+            GrabError::KeyHookError(code) => {
+                write!(f, "Could not set the keyboard hook (error {})", code)
+            }
+            GrabError::MouseHookError(code) => {
+                write!(f, "Could not set the mouse hook (error {})", code)
+            }
+            GrabError::SimulateError(err) => write!(f, "{}", err),
+            GrabError::IoError(err) => write!(f, "{}", err),
+            GrabError::PermissionDenied(path) => permission_denied(f, path),
+            GrabError::UinputUnavailable => uinput_unavailable(f),
+        }
+    }
 }
+
+impl std::error::Error for GrabError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GrabError::SimulateError(err) => Some(err),
+            GrabError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 /// Errors that occur when trying to get display size.
 #[non_exhaustive]
 #[derive(Debug)]
 pub enum DisplayError {
     NoDisplay,
     ConversionError,
+    /// Linux (Wayland), the compositor could not be asked for its outputs,
+    /// only Sway and Hyprland are supported.
+    UnsupportedCompositor,
+}
+
+impl Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayError::NoDisplay => write!(f, "No display or compositor found"),
+            DisplayError::ConversionError => write!(f, "Display size out of range"),
+            DisplayError::UnsupportedCompositor => write!(
+                f,
+                "Could not ask the compositor for its outputs, only Sway and Hyprland are supported"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for DisplayError {}
+
 impl From<SimulateError> for GrabError {
-    fn from(_: SimulateError) -> GrabError {
-        GrabError::SimulateError
+    fn from(err: SimulateError) -> GrabError {
+        GrabError::SimulateError(err)
     }
 }
 
@@ -77,17 +267,120 @@ impl From<std::io::Error> for GrabError {
     }
 }
 
-/// Marking an error when we tried to simulate and event
+/// Errors that occur when trying to simulate an event.
 #[derive(Debug)]
-pub struct SimulateError;
+#[non_exhaustive]
+pub enum SimulateError {
+    /// The event type can't be sent on this platform, e.g. touches or switches.
+    UnsupportedEvent(EventType),
+    /// The key has no code on this platform.
+    UnmappedKey(Key),
+    /// The button has no code on this platform.
+    UnmappedButton(Button),
+    /// A value doesn't fit what the platform can send, e.g. a huge wheel delta.
+    OutOfRange,
+    /// The system did not take the event, e.g. `SendInput` was blocked on Windows.
+    Rejected,
+    /// The display is needed to send the event, e.g. for absolute moves on Wayland.
+    Display(DisplayError),
+    /// The keyboard layout could not be loaded, e.g. for `simulate_text`.
+    KeyboardError,
+    /// The keyboard layout can't type these characters, so `simulate_text`
+    /// typed nothing.
+    UnmappedChars(Vec<char>),
+    /// Linux, access to `/dev/uinput` was refused, it needs root or a udev rule.
+    PermissionDenied(PathBuf),
+    /// Linux, `/dev/uinput` is missing, the `uinput` kernel module is not loaded.
+    UinputUnavailable,
+    /// Linux, setting up or writing to the uinput device failed.
+    IoError(std::io::Error),
+}
 
 impl Display for SimulateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Could not simulate event")
+        match self {
+            SimulateError::UnsupportedEvent(event_type) => {
+                write!(f, "Cannot simulate {} on this platform", event_type)
+            }
+            SimulateError::UnmappedKey(key) => {
+                write!(f, "No code for key {} on this platform", key)
+            }
+            SimulateError::UnmappedButton(button) => {
+                write!(f, "No code for button {} on this platform", button)
+            }
+            SimulateError::OutOfRange => write!(f, "Event value out of range"),
+            SimulateError::Rejected => write!(f, "The system did not accept the event"),
+            SimulateError::Display(err) => write!(f, "{}", err),
+            SimulateError::KeyboardError => write!(f, "Could not load the keyboard layout"),
+            SimulateError::UnmappedChars(chars) => {
+                let chars: String = chars.iter().collect();
+                write!(f, "The keyboard layout can't type {:?}", chars)
+            }
+            SimulateError::PermissionDenied(path) => permission_denied(f, path),
+            SimulateError::UinputUnavailable => uinput_unavailable(f),
+            SimulateError::IoError(err) => write!(f, "{}", err),
+        }
     }
 }
 
-impl std::error::Error for SimulateError {}
+impl std::error::Error for SimulateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SimulateError::Display(err) => Some(err),
+            SimulateError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SimulateError {
+    fn from(err: std::io::Error) -> SimulateError {
+        SimulateError::IoError(err)
+    }
+}
+
+impl From<DisplayError> for SimulateError {
+    fn from(err: DisplayError) -> SimulateError {
+        SimulateError::Display(err)
+    }
+}
+
+fn permission_denied(f: &mut fmt::Formatter, path: &Path) -> fmt::Result {
+    write!(
+        f,
+        "Permission denied on {}, run as root or add the user to the `input` group",
+        path.display()
+    )
+}
+
+fn uinput_unavailable(f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+        f,
+        "/dev/uinput is missing, load the uinput kernel module (`modprobe uinput`)"
+    )
+}
+
+/// A key, button or event name that `FromStr` could not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNameError {
+    name: String,
+}
+
+impl ParseNameError {
+    pub(crate) fn new(name: &str) -> ParseNameError {
+        ParseNameError {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Display for ParseNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown name `{}`", self.name)
+    }
+}
+
+impl std::error::Error for ParseNameError {}
 
 /// Key names based on physical location on the device
 /// Merge Option(MacOS) and Alt(Windows, Linux) into Alt
@@ -229,11 +522,47 @@ pub enum Key {
     PlayPause,
     PlayCd,
     NextTrack,
+    /// Menu key, next to the right Meta key
+    ContextMenu,
+    Power,
+    Sleep,
+    Wake,
+    Calculator,
+    Mail,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    /// Stops media playback
+    Stop,
+    Eject,
+    /// JIS keyboards, left of right Shift
+    Ro,
+    /// JIS keyboards, left of Backspace
+    Yen,
+    /// JIS keyboards, right of Space (conversion)
+    Henkan,
+    /// JIS keyboards, left of Space (no conversion)
+    Muhenkan,
+    /// Korean keyboards, Hangul/English toggle
+    Hangul,
+    /// Korean keyboards, Hanja conversion
+    Hanja,
+    Help,
+    Undo,
+    Copy,
+    Paste,
+    Cut,
     Unknown(u32),
 }
 
 /// Standard mouse buttons
-/// Some mice have more than 3 buttons. These are not defined, and different
+/// `Back` and `Forward` are the thumb buttons (BTN_SIDE/BTN_EXTRA on Linux,
+/// buttons 8/9 on X11, XBUTTON1/XBUTTON2 on Windows, 3/4 on MacOS).
+/// Some mice have even more buttons. These are not defined, and different
 /// OSs will give different `Button::Unknown` values.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -241,6 +570,8 @@ pub enum Button {
     Left,
     Right,
     Middle,
+    Back,
+    Forward,
     Unknown(u8),
 }
 
@@ -253,6 +584,11 @@ pub enum EventType {
     /// To the actual letter a user would use, that requires some layout logic to be added.
     KeyPress(Key),
     KeyRelease(Key),
+    /// The key is held down and the system repeats it. Listeners on Wayland never
+    /// report it, libinput leaves autorepeat to the compositor. Neither do they
+    /// on X11 in practice: the recorded repeats of the default XKB autorepeat
+    /// come as a `KeyRelease` followed by a `KeyPress`.
+    KeyRepeat(Key),
     /// Mouse Button
     ButtonPress(Button),
     ButtonRelease(Button),
@@ -262,6 +598,14 @@ pub enum EventType {
         x: f64,
         y: f64,
     },
+    /// Motion of the pointer device, not a position. Listeners only report it
+    /// where the OS gives device deltas (the Wayland listener, raw unaccelerated
+    /// values). When simulated, the pointer moves by that amount, subject to
+    /// pointer acceleration on some platforms.
+    MouseMoveRelative {
+        dx: f64,
+        dy: f64,
+    },
     /// `delta_y` represents vertical scroll and `delta_x` represents horizontal scroll.
     /// Positive values correspond to scrolling up or right and negative values
     /// correspond to scrolling down or left
@@ -269,6 +613,164 @@ pub enum EventType {
         delta_x: i64,
         delta_y: i64,
     },
+    /// High resolution scrolling, in wheel notches (`1.0` is one click of a
+    /// regular wheel) with the same directions as `Wheel`. Fractions come from
+    /// free-spinning and high resolution wheels, touchpads and trackpoints.
+    /// Listeners that report it still report `Wheel` once whole notches add up.
+    /// When simulated, backends without fractional scrolling (X11) keep the
+    /// fractions until they add up to whole clicks.
+    Scroll {
+        delta_x: f64,
+        delta_y: f64,
+        source: ScrollSource,
+    },
+    /// Touchpad swipe, `dx` and `dy` are the motion since the previous update.
+    /// Gestures are only reported by the Wayland listener and cannot be simulated.
+    Swipe {
+        phase: GesturePhase,
+        fingers: u32,
+        dx: f64,
+        dy: f64,
+    },
+    /// Touchpad pinch, `scale` is relative to the distance between the fingers
+    /// at the beginning (starts at 1.0), `rotation` is the change in degrees
+    /// since the previous update, positive when clockwise.
+    Pinch {
+        phase: GesturePhase,
+        fingers: u32,
+        dx: f64,
+        dy: f64,
+        scale: f64,
+        rotation: f64,
+    },
+    /// Fingers resting on the touchpad without moving. There is no update phase.
+    Hold {
+        phase: GesturePhase,
+        fingers: u32,
+    },
+    /// A finger touched a touchscreen, at a position in pixels like `MouseMove`.
+    /// `id` identifies the touch until it ends, ids are reused afterwards.
+    /// Touches are only reported on Linux (Wayland listener and `grab`) and cannot be simulated.
+    TouchDown {
+        id: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchMove {
+        id: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchUp {
+        id: u32,
+    },
+    /// The touch ended without being released, e.g. the compositor took it for a gesture.
+    TouchCancel {
+        id: u32,
+    },
+    /// The touch events since the previous frame happened at the same time.
+    TouchFrame,
+    /// A drawing tablet tool came close to (`in_proximity`) or left the tablet.
+    /// Tablets are only reported by the Wayland listener and cannot be simulated.
+    TabletProximity {
+        tool: ToolType,
+        in_proximity: bool,
+        axes: TabletAxes,
+    },
+    /// The tool touched (`down`) or left the surface of the tablet.
+    TabletTip {
+        tool: ToolType,
+        down: bool,
+        axes: TabletAxes,
+    },
+    /// The tool moved, or its pressure or tilt changed.
+    TabletAxis {
+        tool: ToolType,
+        axes: TabletAxes,
+    },
+    /// A button on the tool, `button` is the Linux code (BTN_STYLUS, BTN_STYLUS2...).
+    TabletButton {
+        tool: ToolType,
+        button: u32,
+        pressed: bool,
+    },
+    /// A button on the tablet pad, numbered from 0.
+    TabletPadButton {
+        button: u32,
+        pressed: bool,
+    },
+    /// A switch changed state, e.g. the lid was closed (`on`) or opened.
+    /// Switches are only reported on Linux (Wayland listener and `grab`) and cannot be simulated.
+    Switch {
+        kind: SwitchKind,
+        on: bool,
+    },
+}
+
+/// Hardware switches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SwitchKind {
+    /// Laptop lid, on when closed.
+    Lid,
+    /// Convertible laptops, on in tablet mode.
+    TabletMode,
+    /// Other Linux switches (EV_SW codes), e.g. headphone jacks.
+    Unknown(u16),
+}
+
+/// Kind of a drawing tablet tool.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ToolType {
+    Pen,
+    Eraser,
+    Brush,
+    Pencil,
+    Airbrush,
+    /// A mouse used on the tablet.
+    Mouse,
+    /// A mouse with a lens to aim.
+    Lens,
+    /// A rotary device put on the tablet.
+    Totem,
+    Unknown,
+}
+
+/// State of a drawing tablet tool. `x` and `y` are in pixels like `MouseMove`,
+/// `pressure` goes from 0.0 to 1.0 and tilts are in degrees from the
+/// perpendicular, positive towards the right and the bottom.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TabletAxes {
+    pub x: f64,
+    pub y: f64,
+    pub pressure: f64,
+    pub tilt_x: f64,
+    pub tilt_y: f64,
+}
+
+/// Stage of a touchpad gesture, the finger count does not change in between.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum GesturePhase {
+    Begin,
+    Update,
+    End,
+    /// The gesture ended without completing, e.g. another finger was added.
+    Cancel,
+}
+
+/// What produced a `Scroll` event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ScrollSource {
+    /// A mouse wheel, possibly high resolution.
+    Wheel,
+    /// Fingers on a touchpad.
+    Finger,
+    /// A device scrolling while moved, like a trackpoint with a button held.
+    Continuous,
 }
 
 /// When events arrive from the OS they get some additional information added from
@@ -281,8 +783,203 @@ pub enum EventType {
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Event {
     pub time: SystemTime,
+    /// Timestamp the OS gave to the event, unaffected by callback scheduling.
+    /// It comes from a monotonic clock whose origin depends on the platform, so
+    /// only compare it with timestamps of other events: `CLOCK_MONOTONIC` for
+    /// evdev and libinput, the X server time (milliseconds) on X11, the hook
+    /// time (milliseconds since boot) on Windows, and the event timestamp
+    /// (nanoseconds since boot) on MacOS.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub timestamp: Option<Duration>,
     pub name: Option<String>,
     pub event_type: EventType,
+    /// The input device the event comes from, only known when events are read
+    /// from the devices directly (the evdev `grab` loop and the Wayland listener).
+    /// Events of a device share its info.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub device: Option<Arc<DeviceInfo>>,
+    /// Whether the event was injected by rdev, by another program, or comes from hardware.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub source: EventSource,
+    /// Modifiers held and locks active once the event happened, so pressing
+    /// `ControlLeft` reports `CTRL` and releasing it doesn't.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub modifiers: Modifiers,
+    /// Code the platform gave to the key of key events, `None` for other events.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub raw_code: Option<RawCode>,
+}
+
+/// Key code as the platform reported it, before it was mapped to a `Key`, to tell
+/// apart keys rdev maps to the same `Key` or to `Key::Unknown`.
+/// The tables of [`crate::keycodes`] translate the codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum RawCode {
+    /// Linux evdev code, from the `grab` loop and the Wayland listener.
+    Evdev(u32),
+    /// X11 keycode.
+    X11(u32),
+    /// Windows virtual-key code and hardware scan code, `extended` for the
+    /// scan codes prefixed with 0xE0 (e.g. the right Control or keypad Enter).
+    Windows {
+        vk: u16,
+        scan_code: u32,
+        extended: bool,
+    },
+    /// MacOS virtual key code.
+    MacOS(u16),
+    /// MacOS special key code (`NX_KEYTYPE_*`) of media and system keys.
+    MacOSSpecialKey(u32),
+}
+
+bitflags! {
+    /// Modifier keys and keyboard locks.
+    /// X11, Windows and MacOS ask the system. The evdev `grab` loop and the Wayland
+    /// listener follow the key events they see, so locks start off and modifiers
+    /// held before listening are missed until released.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+    pub struct Modifiers: u8 {
+        const SHIFT = 1;
+        const CTRL = 1 << 1;
+        const ALT = 1 << 2;
+        const ALT_GR = 1 << 3;
+        /// Windows, Command or Super key.
+        const META = 1 << 4;
+        const CAPS_LOCK = 1 << 5;
+        const NUM_LOCK = 1 << 6;
+    }
+}
+
+impl Modifiers {
+    /// The modifier a key controls.
+    pub fn from_key(key: Key) -> Modifiers {
+        match key {
+            Key::ShiftLeft | Key::ShiftRight => Modifiers::SHIFT,
+            Key::ControlLeft | Key::ControlRight => Modifiers::CTRL,
+            Key::Alt => Modifiers::ALT,
+            Key::AltGr => Modifiers::ALT_GR,
+            Key::MetaLeft | Key::MetaRight => Modifiers::META,
+            Key::CapsLock => Modifiers::CAPS_LOCK,
+            Key::NumLock => Modifiers::NUM_LOCK,
+            _ => Modifiers::empty(),
+        }
+    }
+
+    /// Applies a key event: modifiers follow the key, locks toggle when pressed.
+    /// `held` tells whether a key is still down, a modifier with a key on each
+    /// side is only released once both are up.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(crate) fn update(&mut self, event_type: &EventType, held: impl Fn(Key) -> bool) {
+        let locks = Modifiers::CAPS_LOCK | Modifiers::NUM_LOCK;
+        match event_type {
+            EventType::KeyPress(key) => {
+                let modifier = Modifiers::from_key(*key);
+                if locks.contains(modifier) {
+                    self.toggle(modifier);
+                } else {
+                    self.insert(modifier);
+                }
+            }
+            EventType::KeyRelease(key) => {
+                let modifier = Modifiers::from_key(*key);
+                if !locks.contains(modifier) && !other_side(*key).is_some_and(&held) {
+                    self.remove(modifier);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The key on the other side of the keyboard for the same modifier.
+fn other_side(key: Key) -> Option<Key> {
+    match key {
+        Key::ShiftLeft => Some(Key::ShiftRight),
+        Key::ShiftRight => Some(Key::ShiftLeft),
+        Key::ControlLeft => Some(Key::ControlRight),
+        Key::ControlRight => Some(Key::ControlLeft),
+        Key::MetaLeft => Some(Key::MetaRight),
+        Key::MetaRight => Some(Key::MetaLeft),
+        _ => None,
+    }
+}
+
+/// Modifiers followed from key events alone, for the backends that can't ask
+/// the system. The modifier keys held are kept to tell the sides apart.
+#[derive(Debug, Default)]
+#[cfg_attr(
+    not(all(
+        target_family = "unix",
+        not(target_os = "macos"),
+        any(feature = "wayland", feature = "unstable_grab")
+    )),
+    allow(dead_code)
+)]
+pub(crate) struct ModifierKeys {
+    modifiers: Modifiers,
+    held: Vec<Key>,
+}
+
+#[cfg_attr(
+    not(all(
+        target_family = "unix",
+        not(target_os = "macos"),
+        any(feature = "wayland", feature = "unstable_grab")
+    )),
+    allow(dead_code)
+)]
+impl ModifierKeys {
+    /// Applies a key event, returns the modifiers after it (unchanged for other events).
+    pub(crate) fn update(&mut self, event_type: &EventType) -> Modifiers {
+        match event_type {
+            EventType::KeyPress(key)
+                if !Modifiers::from_key(*key).is_empty() && !self.held.contains(key) =>
+            {
+                self.held.push(*key)
+            }
+            EventType::KeyRelease(key) => self.held.retain(|held| held != key),
+            _ => {}
+        }
+        let held = &self.held;
+        self.modifiers.update(event_type, |key| held.contains(&key));
+        self.modifiers
+    }
+}
+
+/// Where an event comes from, so that a program simulating events can
+/// recognize (and ignore) its own events when it also listens.
+/// Detection is best effort and platform dependent:
+/// - Linux (evdev `grab`, Wayland): from the device identity, rdev's own uinput device
+///   is `Rdev`, other virtual devices are `OtherSynthetic`.
+/// - X11: from XTest requests, only events simulated from the same process are `Rdev`.
+/// - Windows: from the injected flag of the low level hooks.
+/// - MacOS: from the event source user data, events with a source state other
+///   than the HID system are `OtherSynthetic`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum EventSource {
+    #[default]
+    Hardware,
+    /// Simulated with rdev's `simulate`.
+    Rdev,
+    /// Injected by another program.
+    OtherSynthetic,
+}
+
+/// Identifies the physical (or virtual) input device that sent an event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DeviceInfo {
+    /// Name the device reports, e.g. "AT Translated Set 2 keyboard".
+    pub name: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// Device node, e.g. `/dev/input/event3`.
+    pub path: Option<PathBuf>,
+    /// Kernel name of the device node, e.g. `event3` (what libinput calls sysname).
+    pub sysname: Option<String>,
 }
 
 /// We can define a dummy Keyboard, that we will use to detect
@@ -0,0 +1,125 @@
+use crate::rdev::{Event, ListenError, ListenHandle};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+#[derive(Default)]
+struct Shared {
+    /// Unbounded, see `listen_stream`.
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+    finished: bool,
+}
+
+/// `Stream` of global events returned by `listen_stream`.
+/// Dropping the stream stops the background listener.
+pub struct EventStream {
+    shared: Arc<Mutex<Shared>>,
+    handle: ListenHandle,
+    thread: Option<JoinHandle<Result<(), ListenError>>>,
+}
+
+impl EventStream {
+    /// Stops the background listener and waits for it to finish.
+    /// Returns the error of the listener if it could not start.
+    /// This blocks the current thread for a short while, dropping the stream
+    /// stops the listener without waiting.
+    pub fn stop(mut self) -> Result<(), ListenError> {
+        self.handle.stop();
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            // The listener thread panicked, there is no `ListenError` to report.
+            Some(Err(_)) | None => Ok(()),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let mut shared = match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(_) => return Poll::Ready(None),
+        };
+        if let Some(event) = shared.events.pop_front() {
+            Poll::Ready(Some(event))
+        } else if shared.finished {
+            Poll::Ready(None)
+        } else {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.handle.stop();
+    }
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+fn push(shared: &Mutex<Shared>, event: Option<Event>) {
+    if let Ok(mut shared) = shared.lock() {
+        match event {
+            Some(event) => shared.events.push_back(event),
+            None => shared.finished = true,
+        }
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Listens to global events as a `futures::Stream`, so they can be awaited
+/// next to other async work. Requires the `async` feature.
+///
+/// Event taps and hooks block, so the listener runs on its own thread. A hook
+/// can't wait for the stream without stalling the input of the whole system,
+/// so events queue up until they are polled and the queue has no limit: keep
+/// polling the stream, or drop it to stop listening.
+/// Caveat: On MacOS, the listener does not run on the main thread so
+/// `set_is_main_thread(false)` needs to be called first.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use rdev::listen_stream;
+///
+/// # async fn run() {
+/// let mut events = listen_stream();
+/// while let Some(event) = events.next().await {
+///     println!("Received {:?}", event);
+/// }
+/// # }
+/// ```
+pub fn listen_stream() -> EventStream {
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let handle = ListenHandle::new();
+    let listen_handle = handle.clone();
+    let listen_shared = shared.clone();
+    let thread = thread::spawn(move || {
+        let callback_shared = listen_shared.clone();
+        let result = crate::listen_until(
+            move |event| push(&callback_shared, Some(event)),
+            &listen_handle,
+        );
+        push(&listen_shared, None);
+        result
+    });
+    EventStream {
+        shared,
+        handle,
+        thread: Some(thread),
+    }
+}
@@ -0,0 +1,298 @@
+use crate::rdev::{EventType, Key, KeyboardState, Modifiers, SimulateError};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+/// Levels of a key: plain, with Shift, with AltGr (the third level on Linux,
+/// the right Option on MacOS) and with both.
+const LEVELS: [Modifiers; 4] = [
+    Modifiers::empty(),
+    Modifiers::SHIFT,
+    Modifiers::ALT_GR,
+    Modifiers::SHIFT.union(Modifiers::ALT_GR),
+];
+
+const SPACE: Stroke = Stroke {
+    key: Key::Space,
+    modifiers: Modifiers::empty(),
+};
+
+/// A key typed with the modifiers of its level held.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Stroke {
+    key: Key,
+    modifiers: Modifiers,
+}
+
+impl Stroke {
+    fn new(key: Key, modifiers: Modifiers) -> Stroke {
+        Stroke { key, modifiers }
+    }
+
+    /// Modifiers pressed, the key pressed and released, modifiers released.
+    pub(crate) fn events(&self) -> Vec<EventType> {
+        let modifiers: Vec<Key> = [
+            (Modifiers::SHIFT, Key::ShiftLeft),
+            (Modifiers::ALT_GR, Key::AltGr),
+        ]
+        .into_iter()
+        .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+        .map(|(_, key)| key)
+        .collect();
+        let mut events: Vec<EventType> = modifiers
+            .iter()
+            .map(|key| EventType::KeyPress(*key))
+            .collect();
+        events.push(EventType::KeyPress(self.key));
+        events.push(EventType::KeyRelease(self.key));
+        events.extend(
+            modifiers
+                .iter()
+                .rev()
+                .map(|key| EventType::KeyRelease(*key)),
+        );
+        events
+    }
+}
+
+/// Text the strokes produce, starting from a reset keyboard.
+fn output<K: KeyboardState + ?Sized>(keyboard: &mut K, strokes: &[Stroke]) -> String {
+    keyboard.reset();
+    let mut text = String::new();
+    for event_type in strokes.iter().flat_map(Stroke::events) {
+        if let Some(string) = keyboard.add(&event_type) {
+            text.push_str(&string);
+        }
+    }
+    text
+}
+
+/// The character of an output made of a single printable character.
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_control() => Some(c),
+        _ => None,
+    }
+}
+
+/// Finds the strokes typing `text` with the layout `keyboard` follows: every
+/// key is tried on every level, then dead keys (keys typing nothing, but
+/// something else than a space once followed by Space) are combined with the
+/// other keys for the characters still missing.
+/// Fails with the characters no stroke produces.
+pub(crate) fn plan<K: KeyboardState + ?Sized>(
+    keyboard: &mut K,
+    text: &str,
+) -> Result<Vec<Stroke>, SimulateError> {
+    let mut sequences: HashMap<char, Vec<Stroke>> = HashMap::new();
+    sequences.insert('\n', vec![Stroke::new(Key::Return, Modifiers::empty())]);
+    sequences.insert('\t', vec![Stroke::new(Key::Tab, Modifiers::empty())]);
+
+    let mut bases = vec![];
+    let mut dead_keys = vec![];
+    let keys = Key::VARIANTS
+        .iter()
+        .copied()
+        .filter(|key| Modifiers::from_key(*key).is_empty());
+    for key in keys {
+        for modifiers in LEVELS {
+            let stroke = Stroke::new(key, modifiers);
+            let text = output(keyboard, &[stroke]);
+            if let Some(c) = single_char(&text) {
+                bases.push(stroke);
+                sequences.entry(c).or_insert_with(|| vec![stroke]);
+            } else if text.is_empty() && !matches!(&*output(keyboard, &[stroke, SPACE]), "" | " ") {
+                dead_keys.push(stroke);
+            }
+        }
+    }
+
+    if text.chars().any(|c| !sequences.contains_key(&c)) {
+        for dead_key in dead_keys {
+            for base in &bases {
+                if let Some(c) = single_char(&output(keyboard, &[dead_key, *base])) {
+                    sequences.entry(c).or_insert_with(|| vec![dead_key, *base]);
+                }
+            }
+        }
+    }
+    keyboard.reset();
+
+    let mut strokes = vec![];
+    let mut unmapped = vec![];
+    for c in text.chars() {
+        match sequences.get(&c) {
+            Some(sequence) => strokes.extend(sequence),
+            None if !unmapped.contains(&c) => unmapped.push(c),
+            None => {}
+        }
+    }
+    if unmapped.is_empty() {
+        Ok(strokes)
+    } else {
+        Err(SimulateError::UnmappedChars(unmapped))
+    }
+}
+
+/// Sends the events of `strokes` with `simulate`, waiting `delay` after each one.
+/// When an event fails, the keys still held are released before returning its error.
+pub(crate) fn send<F>(
+    strokes: &[Stroke],
+    delay: Duration,
+    mut simulate: F,
+) -> Result<(), SimulateError>
+where
+    F: FnMut(&EventType) -> Result<(), SimulateError>,
+{
+    let mut held = vec![];
+    for event_type in strokes.iter().flat_map(Stroke::events) {
+        if let Err(error) = simulate(&event_type) {
+            for key in held.iter().rev() {
+                // The first error is the one worth reporting.
+                let _ = simulate(&EventType::KeyRelease(*key));
+            }
+            return Err(error);
+        }
+        match event_type {
+            EventType::KeyPress(key) => held.push(key),
+            EventType::KeyRelease(key) => held.retain(|held| *held != key),
+            _ => {}
+        }
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few keys of a layout with AltGr and a dead acute accent.
+    #[derive(Default)]
+    struct Layout {
+        shift: bool,
+        alt_gr: bool,
+        acute: bool,
+    }
+
+    impl KeyboardState for Layout {
+        fn add(&mut self, event_type: &EventType) -> Option<String> {
+            let key = match event_type {
+                EventType::KeyPress(key) => *key,
+                EventType::KeyRelease(Key::ShiftLeft) => {
+                    self.shift = false;
+                    return None;
+                }
+                EventType::KeyRelease(Key::AltGr) => {
+                    self.alt_gr = false;
+                    return None;
+                }
+                _ => return None,
+            };
+            let c = match (key, self.shift, self.alt_gr) {
+                (Key::ShiftLeft, _, _) => {
+                    self.shift = true;
+                    return None;
+                }
+                (Key::AltGr, _, _) => {
+                    self.alt_gr = true;
+                    return None;
+                }
+                (Key::Quote, false, false) => {
+                    self.acute = true;
+                    return Some(String::new());
+                }
+                (Key::KeyE, false, false) => 'e',
+                (Key::KeyE, true, false) => 'E',
+                (Key::KeyE, false, true) => '€',
+                (Key::Space, false, false) => ' ',
+                _ => return None,
+            };
+            let c = match (std::mem::take(&mut self.acute), c) {
+                (true, 'e') => 'é',
+                (true, 'E') => 'É',
+                (true, ' ') => '´',
+                (_, c) => c,
+            };
+            Some(c.to_string())
+        }
+
+        fn reset(&mut self) {
+            *self = Layout::default();
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let e = Stroke::new(Key::KeyE, Modifiers::empty());
+        let shift_e = Stroke::new(Key::KeyE, Modifiers::SHIFT);
+        let alt_gr_e = Stroke::new(Key::KeyE, Modifiers::ALT_GR);
+        let acute = Stroke::new(Key::Quote, Modifiers::empty());
+        let strokes = plan(&mut Layout::default(), "eE€ é\nÉ´").unwrap();
+        assert_eq!(
+            strokes,
+            [
+                e,
+                shift_e,
+                alt_gr_e,
+                SPACE,
+                acute,
+                e,
+                Stroke::new(Key::Return, Modifiers::empty()),
+                acute,
+                shift_e,
+                acute,
+                SPACE,
+            ]
+        );
+        assert!(matches!(
+            plan(&mut Layout::default(), "eßèß"),
+            Err(SimulateError::UnmappedChars(chars)) if chars == ['ß', 'è']
+        ));
+    }
+
+    #[test]
+    fn test_stroke_events() {
+        let stroke = Stroke::new(Key::KeyE, Modifiers::SHIFT | Modifiers::ALT_GR);
+        assert_eq!(
+            stroke.events(),
+            [
+                EventType::KeyPress(Key::ShiftLeft),
+                EventType::KeyPress(Key::AltGr),
+                EventType::KeyPress(Key::KeyE),
+                EventType::KeyRelease(Key::KeyE),
+                EventType::KeyRelease(Key::AltGr),
+                EventType::KeyRelease(Key::ShiftLeft),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_send_releases_modifiers() {
+        let strokes = [
+            Stroke::new(Key::KeyE, Modifiers::empty()),
+            Stroke::new(Key::KeyQ, Modifiers::SHIFT | Modifiers::ALT_GR),
+        ];
+        let mut sent = vec![];
+        let result = send(&strokes, Duration::ZERO, |event_type| {
+            if *event_type == EventType::KeyPress(Key::KeyQ) {
+                return Err(SimulateError::Rejected);
+            }
+            sent.push(*event_type);
+            Ok(())
+        });
+        assert!(matches!(result, Err(SimulateError::Rejected)));
+        assert_eq!(
+            sent,
+            [
+                EventType::KeyPress(Key::KeyE),
+                EventType::KeyRelease(Key::KeyE),
+                EventType::KeyPress(Key::ShiftLeft),
+                EventType::KeyPress(Key::AltGr),
+                EventType::KeyRelease(Key::AltGr),
+                EventType::KeyRelease(Key::ShiftLeft),
+            ]
+        );
+    }
+}
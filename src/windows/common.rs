@@ -1,25 +1,37 @@
-use crate::rdev::{Button, EventType};
+use crate::keycodes::windows_vk::{code_from_key, key_from_code};
+use crate::rdev::{Button, EventSource, EventType, ListenHandle, Modifiers, RawCode};
 use crate::windows::keyboard::Keyboard;
-use crate::windows::keycodes::key_from_code;
 use lazy_static::lazy_static;
+use std::cell::RefCell;
 use std::convert::TryInto;
 use std::os::raw::{c_int, c_short};
 use std::ptr::null_mut;
 use std::sync::Mutex;
+use std::thread::LocalKey;
+use std::time::Duration;
+use winapi::shared::basetsd::ULONG_PTR;
 use winapi::shared::minwindef::{DWORD, HIWORD, LPARAM, LRESULT, WORD, WPARAM};
 use winapi::shared::ntdef::LONG;
 use winapi::shared::windef::HHOOK;
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
 use winapi::um::winuser::{
-    KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT, SetWindowsHookExA, WH_KEYBOARD_LL, WH_MOUSE_LL, WHEEL_DELTA,
-    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP,
-    WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN,
-    WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
+    GetAsyncKeyState, GetKeyState, GetMessageA, KBDLLHOOKSTRUCT, LLKHF_EXTENDED, LLKHF_INJECTED,
+    LLMHF_INJECTED, MSG, MSLLHOOKSTRUCT, PM_NOREMOVE, PeekMessageA, PostThreadMessageA,
+    SetWindowsHookExA, UnhookWindowsHookEx, VK_CAPITAL, VK_CONTROL, VK_LMENU, VK_LWIN, VK_NUMLOCK,
+    VK_RMENU, VK_RWIN, VK_SHIFT, WH_KEYBOARD_LL, WH_MOUSE_LL, WHEEL_DELTA, WM_KEYDOWN, WM_KEYUP,
+    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE,
+    WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_USER,
+    WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
 };
 pub const TRUE: i32 = 1;
 pub const FALSE: i32 = 0;
 
-pub static mut HOOK: HHOOK = null_mut();
+thread_local! {
+    /// Virtual keys held down, low level hooks don't tell autorepeat from a new press.
+    /// Hooks are called on the thread that installed them, so each loop has its own.
+    static PRESSED_KEYS: RefCell<[bool; 256]> = const { RefCell::new([false; 256]) };
+}
 lazy_static! {
     pub(crate) static ref KEYBOARD: Mutex<Keyboard> = Mutex::new(Keyboard::new().unwrap());
 }
@@ -58,17 +70,126 @@ pub unsafe fn get_button_code(lpdata: LPARAM) -> WORD {
     }
 }
 
+pub fn button_from_code(code: WORD) -> Button {
+    match code {
+        XBUTTON1 => Button::Back,
+        XBUTTON2 => Button::Forward,
+        code => Button::Unknown(code as u8),
+    }
+}
+
+/// Stored in `dwExtraInfo` of the inputs `simulate` sends, to recognize them in the hooks.
+pub const RDEV_EXTRA_INFO: ULONG_PTR = 0x7264_6576;
+
+/// Hook time, in milliseconds since boot (same clock as `GetTickCount`).
+pub unsafe fn get_timestamp(param: WPARAM, lpdata: LPARAM) -> Duration {
+    unsafe {
+        let time = match param.try_into() {
+            Ok(WM_KEYDOWN) | Ok(WM_SYSKEYDOWN) | Ok(WM_KEYUP) | Ok(WM_SYSKEYUP) => {
+                (*(lpdata as *const KBDLLHOOKSTRUCT)).time
+            }
+            _ => (*(lpdata as *const MSLLHOOKSTRUCT)).time,
+        };
+        Duration::from_millis(time.into())
+    }
+}
+
+pub unsafe fn get_source(param: WPARAM, lpdata: LPARAM) -> EventSource {
+    unsafe {
+        let (injected, extra_info) = match param.try_into() {
+            Ok(WM_KEYDOWN) | Ok(WM_SYSKEYDOWN) | Ok(WM_KEYUP) | Ok(WM_SYSKEYUP) => {
+                let kb = *(lpdata as *const KBDLLHOOKSTRUCT);
+                (kb.flags & LLKHF_INJECTED != 0, kb.dwExtraInfo)
+            }
+            _ => {
+                let mouse = *(lpdata as *const MSLLHOOKSTRUCT);
+                (mouse.flags & LLMHF_INJECTED != 0, mouse.dwExtraInfo)
+            }
+        };
+        if extra_info == RDEV_EXTRA_INFO {
+            EventSource::Rdev
+        } else if injected {
+            EventSource::OtherSynthetic
+        } else {
+            EventSource::Hardware
+        }
+    }
+}
+
+pub unsafe fn get_raw_code(param: WPARAM, lpdata: LPARAM) -> Option<RawCode> {
+    unsafe {
+        match param.try_into() {
+            Ok(WM_KEYDOWN) | Ok(WM_SYSKEYDOWN) | Ok(WM_KEYUP) | Ok(WM_SYSKEYUP) => {
+                let kb = *(lpdata as *const KBDLLHOOKSTRUCT);
+                Some(RawCode::Windows {
+                    vk: kb.vkCode as u16,
+                    scan_code: kb.scanCode,
+                    extended: kb.flags & LLKHF_EXTENDED != 0,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Low level hooks run before the key state is updated, the event is applied on top.
+pub unsafe fn get_modifiers(event_type: &EventType) -> Modifiers {
+    let keys = [
+        (VK_SHIFT, Modifiers::SHIFT),
+        (VK_CONTROL, Modifiers::CTRL),
+        (VK_LMENU, Modifiers::ALT),
+        (VK_RMENU, Modifiers::ALT_GR),
+        (VK_LWIN, Modifiers::META),
+        (VK_RWIN, Modifiers::META),
+    ];
+    let mut modifiers = Modifiers::empty();
+    for (key, modifier) in keys {
+        // The high bit is set while the key is down.
+        if unsafe { GetAsyncKeyState(key) } < 0 {
+            modifiers |= modifier;
+        }
+    }
+    for (key, modifier) in [
+        (VK_CAPITAL, Modifiers::CAPS_LOCK),
+        (VK_NUMLOCK, Modifiers::NUM_LOCK),
+    ] {
+        // The low bit is set while the lock is on.
+        if unsafe { GetKeyState(key) } & 1 != 0 {
+            modifiers |= modifier;
+        }
+    }
+    // `VK_SHIFT` and the like are down while either side is.
+    modifiers.update(event_type, |key| {
+        code_from_key(key).is_some_and(|vk| unsafe { GetAsyncKeyState(vk.into()) } < 0)
+    });
+    modifiers
+}
+
 pub unsafe fn convert(param: WPARAM, lpdata: LPARAM) -> Option<EventType> {
     unsafe {
         match param.try_into() {
             Ok(WM_KEYDOWN) | Ok(WM_SYSKEYDOWN) => {
                 let code = get_code(lpdata);
                 let key = key_from_code(code as u16);
-                Some(EventType::KeyPress(key))
+                let repeat = PRESSED_KEYS.with_borrow_mut(|pressed| {
+                    pressed
+                        .get_mut(code as usize)
+                        .is_some_and(|pressed| std::mem::replace(pressed, true))
+                });
+                if repeat {
+                    Some(EventType::KeyRepeat(key))
+                } else {
+                    Some(EventType::KeyPress(key))
+                }
             }
             Ok(WM_KEYUP) | Ok(WM_SYSKEYUP) => {
                 let code = get_code(lpdata);
                 let key = key_from_code(code as u16);
+                PRESSED_KEYS.with_borrow_mut(|pressed| {
+                    if let Some(pressed) = pressed.get_mut(code as usize) {
+                        *pressed = false;
+                    }
+                });
                 Some(EventType::KeyRelease(key))
             }
             Ok(WM_LBUTTONDOWN) => Some(EventType::ButtonPress(Button::Left)),
@@ -78,12 +199,12 @@ pub unsafe fn convert(param: WPARAM, lpdata: LPARAM) -> Option<EventType> {
             Ok(WM_RBUTTONDOWN) => Some(EventType::ButtonPress(Button::Right)),
             Ok(WM_RBUTTONUP) => Some(EventType::ButtonRelease(Button::Right)),
             Ok(WM_XBUTTONDOWN) => {
-                let code = get_button_code(lpdata) as u8;
-                Some(EventType::ButtonPress(Button::Unknown(code)))
+                let button = button_from_code(get_button_code(lpdata));
+                Some(EventType::ButtonPress(button))
             }
             Ok(WM_XBUTTONUP) => {
-                let code = get_button_code(lpdata) as u8;
-                Some(EventType::ButtonRelease(Button::Unknown(code)))
+                let button = button_from_code(get_button_code(lpdata));
+                Some(EventType::ButtonRelease(button))
             }
             Ok(WM_MOUSEMOVE) => {
                 let (x, y) = get_point(lpdata);
@@ -117,7 +238,37 @@ pub enum HookError {
     Key(DWORD),
 }
 
-pub unsafe fn set_key_hook(callback: RawCallback) -> Result<(), HookError> {
+/// An installed low level hook, removed on drop.
+pub struct Hook(HHOOK);
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        unsafe {
+            UnhookWindowsHookEx(self.0);
+        }
+    }
+}
+
+/// The callback of the hooks installed by a thread, cleared on drop with the
+/// keys held so that neither outlives its loop.
+pub struct CallbackGuard<T: 'static>(&'static LocalKey<RefCell<Option<T>>>);
+
+impl<T> CallbackGuard<T> {
+    pub fn set(key: &'static LocalKey<RefCell<Option<T>>>, callback: T) -> Self {
+        key.set(Some(callback));
+        CallbackGuard(key)
+    }
+}
+
+impl<T> Drop for CallbackGuard<T> {
+    fn drop(&mut self) {
+        self.0.set(None);
+        // Keys held when the loop stops would be reported as repeats by the next one.
+        PRESSED_KEYS.set([false; 256]);
+    }
+}
+
+pub unsafe fn set_key_hook(callback: RawCallback) -> Result<Hook, HookError> {
     unsafe {
         let hook = SetWindowsHookExA(WH_KEYBOARD_LL, Some(callback), null_mut(), 0);
 
@@ -125,19 +276,33 @@ pub unsafe fn set_key_hook(callback: RawCallback) -> Result<(), HookError> {
             let error = GetLastError();
             return Err(HookError::Key(error));
         }
-        HOOK = hook;
-        Ok(())
+        Ok(Hook(hook))
     }
 }
 
-pub unsafe fn set_mouse_hook(callback: RawCallback) -> Result<(), HookError> {
+pub unsafe fn set_mouse_hook(callback: RawCallback) -> Result<Hook, HookError> {
     unsafe {
         let hook = SetWindowsHookExA(WH_MOUSE_LL, Some(callback), null_mut(), 0);
         if hook.is_null() {
             let error = GetLastError();
             return Err(HookError::Mouse(error));
         }
-        HOOK = hook;
-        Ok(())
+        Ok(Hook(hook))
+    }
+}
+
+/// Pumps messages so that the low level hooks get called, until `handle` is stopped.
+pub unsafe fn run_message_loop(handle: &ListenHandle) {
+    unsafe {
+        let thread_id = GetCurrentThreadId();
+        let mut msg: MSG = std::mem::zeroed();
+        // Force the creation of the thread message queue, otherwise
+        // PostThreadMessage fails if `stop` is called before GetMessage.
+        PeekMessageA(&mut msg, null_mut(), WM_USER, WM_USER, PM_NOREMOVE);
+        let waker = handle.add_waker(move || {
+            PostThreadMessageA(thread_id, WM_QUIT, 0, 0);
+        });
+        while !handle.is_stopped() && GetMessageA(&mut msg, null_mut(), 0, 0) > 0 {}
+        handle.remove_waker(waker);
     }
 }
@@ -1,10 +1,19 @@
-use crate::rdev::{Event, EventType, GrabError};
-use crate::windows::common::{HOOK, HookError, KEYBOARD, convert, set_key_hook, set_mouse_hook};
+use crate::rdev::{Event, EventType, GrabError, GrabHandle};
+use crate::windows::common::{
+    CallbackGuard, HookError, KEYBOARD, convert, get_modifiers, get_raw_code, get_source,
+    get_timestamp, run_message_loop, set_key_hook, set_mouse_hook,
+};
+use std::cell::RefCell;
 use std::ptr::null_mut;
 use std::time::SystemTime;
-use winapi::um::winuser::{CallNextHookEx, GetMessageA, HC_ACTION};
+use winapi::um::winuser::{CallNextHookEx, HC_ACTION};
 
-static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event) -> Option<Event>>> = None;
+type Callback = Box<dyn FnMut(Event) -> Option<Event>>;
+
+thread_local! {
+    /// The callback of the hooks installed by this thread.
+    static CALLBACK: RefCell<Option<Callback>> = const { RefCell::new(None) };
+}
 
 unsafe extern "system" fn raw_callback(code: i32, param: usize, lpdata: isize) -> isize {
     unsafe {
@@ -12,30 +21,42 @@ unsafe extern "system" fn raw_callback(code: i32, param: usize, lpdata: isize) -
             let opt = convert(param, lpdata);
             if let Some(event_type) = opt {
                 let name = match &event_type {
-                    EventType::KeyPress(_key) => match (*KEYBOARD).lock() {
-                        Ok(mut keyboard) => keyboard.get_name(lpdata),
-                        Err(_) => None,
-                    },
+                    EventType::KeyPress(_key) | EventType::KeyRepeat(_key) => {
+                        match (*KEYBOARD).lock() {
+                            Ok(mut keyboard) => keyboard.get_name(lpdata),
+                            Err(_) => None,
+                        }
+                    }
                     _ => None,
                 };
+                let modifiers = get_modifiers(&event_type);
                 let event = Event {
                     event_type,
                     time: SystemTime::now(),
+                    timestamp: Some(get_timestamp(param, lpdata)),
                     name,
+                    device: None,
+                    source: get_source(param, lpdata),
+                    modifiers,
+                    raw_code: get_raw_code(param, lpdata),
                 };
-                let ptr = &raw mut GLOBAL_CALLBACK;
-                if let Some(callback) = &mut *ptr {
-                    if callback(event).is_none() {
-                        // https://stackoverflow.com/questions/42756284/blocking-windows-mouse-click-using-setwindowshookex
-                        // https://android.developreference.com/article/14560004/Blocking+windows+mouse+click+using+SetWindowsHookEx()
-                        // https://cboard.cprogramming.com/windows-programming/99678-setwindowshookex-wm_keyboard_ll.html
-                        // let _result = CallNextHookEx(HOOK, code, param, lpdata);
-                        return 1;
-                    }
+                // Borrowing fails if the callback itself pumps messages.
+                let blocked = CALLBACK.with(|callback| {
+                    callback.try_borrow_mut().is_ok_and(|mut callback| {
+                        callback
+                            .as_mut()
+                            .is_some_and(|callback| callback(event).is_none())
+                    })
+                });
+                if blocked {
+                    // https://stackoverflow.com/questions/42756284/blocking-windows-mouse-click-using-setwindowshookex
+                    // https://android.developreference.com/article/14560004/Blocking+windows+mouse+click+using+SetWindowsHookEx()
+                    // https://cboard.cprogramming.com/windows-programming/99678-setwindowshookex-wm_keyboard_ll.html
+                    return 1;
                 }
             }
         }
-        CallNextHookEx(HOOK, code, param, lpdata)
+        CallNextHookEx(null_mut(), code, param, lpdata)
     }
 }
 impl From<HookError> for GrabError {
@@ -47,16 +68,16 @@ impl From<HookError> for GrabError {
     }
 }
 
-pub fn grab<T>(callback: T) -> Result<(), GrabError>
+pub fn grab<T>(callback: T, handle: &GrabHandle) -> Result<(), GrabError>
 where
     T: FnMut(Event) -> Option<Event> + 'static,
 {
     unsafe {
-        GLOBAL_CALLBACK = Some(Box::new(callback));
-        set_key_hook(raw_callback)?;
-        set_mouse_hook(raw_callback)?;
+        let _callback = CallbackGuard::set(&CALLBACK, Box::new(callback));
+        let _key_hook = set_key_hook(raw_callback)?;
+        let _mouse_hook = set_mouse_hook(raw_callback)?;
 
-        GetMessageA(null_mut(), null_mut(), 0, 0);
+        run_message_loop(handle);
     }
     Ok(())
 }
@@ -1,19 +1,27 @@
+use crate::keycodes::windows_vk::code_from_key;
 use crate::rdev::{EventType, Key, KeyboardState};
 use crate::windows::common::{FALSE, TRUE, get_code, get_scan_code};
-use crate::windows::keycodes::code_from_key;
 use std::ptr::null_mut;
 use winapi::shared::minwindef::{BYTE, HKL, LPARAM, UINT};
 use winapi::um::processthreadsapi::GetCurrentThreadId;
 use winapi::um::winuser;
 use winapi::um::winuser::{
     GetForegroundWindow, GetKeyState, GetKeyboardLayout, GetKeyboardState,
-    GetWindowThreadProcessId, ToUnicodeEx, VK_CAPITAL, VK_LSHIFT, VK_RSHIFT, VK_SHIFT,
+    GetWindowThreadProcessId, ToUnicodeEx, VK_CAPITAL, VK_CONTROL, VK_LCONTROL, VK_LSHIFT, VK_MENU,
+    VK_RMENU, VK_RSHIFT, VK_SHIFT, VK_SPACE,
 };
 
 const VK_SHIFT_: usize = VK_SHIFT as usize;
 const VK_CAPITAL_: usize = VK_CAPITAL as usize;
 const VK_LSHIFT_: usize = VK_LSHIFT as usize;
 const VK_RSHIFT_: usize = VK_RSHIFT as usize;
+// Windows sees AltGr as the left Control with the right Alt.
+const ALT_GR: [usize; 4] = [
+    VK_CONTROL as usize,
+    VK_LCONTROL as usize,
+    VK_MENU as usize,
+    VK_RMENU as usize,
+];
 const HIGHBIT: u8 = 0x80;
 
 pub struct Keyboard {
@@ -122,6 +130,31 @@ impl Keyboard {
         }
     }
 
+    /// Unlike `get_code_name`, leaves a dead key pending in the system so that
+    /// the next key composes with it.
+    unsafe fn translate(&mut self, code: UINT) -> Option<String> {
+        unsafe {
+            let layout = Self::foreground_layout();
+            const BUF_LEN: i32 = 32;
+            let mut buff = [0_u16; BUF_LEN as usize];
+            let state_ptr = self.last_state.as_mut_ptr();
+            let len = ToUnicodeEx(code, 0, state_ptr, buff.as_mut_ptr(), BUF_LEN, 0, layout);
+            match len {
+                -1 => Some(String::new()),
+                len if len > 0 => String::from_utf16(&buff[..len as usize]).ok(),
+                _ => None,
+            }
+        }
+    }
+
+    unsafe fn foreground_layout() -> HKL {
+        unsafe {
+            let current_window_thread_id =
+                GetWindowThreadProcessId(GetForegroundWindow(), null_mut());
+            GetKeyboardLayout(current_window_thread_id)
+        }
+    }
+
     unsafe fn clear_keyboard_buffer(&self, code: UINT, scan_code: UINT, layout: HKL) {
         unsafe {
             const BUF_LEN: i32 = 32;
@@ -141,6 +174,9 @@ impl Keyboard {
 impl KeyboardState for Keyboard {
     fn add(&mut self, event_type: &EventType) -> Option<String> {
         match event_type {
+            // A repeat types the character again, Caps Lock doesn't toggle again.
+            EventType::KeyRepeat(Key::CapsLock) => None,
+            EventType::KeyRepeat(key) => self.add(&EventType::KeyPress(*key)),
             EventType::KeyPress(key) => match key {
                 Key::ShiftLeft => {
                     self.last_state[VK_SHIFT_] |= HIGHBIT;
@@ -156,9 +192,15 @@ impl KeyboardState for Keyboard {
                     self.last_state[VK_CAPITAL_] ^= 1;
                     None
                 }
+                Key::AltGr => {
+                    for vk in ALT_GR {
+                        self.last_state[vk] |= HIGHBIT;
+                    }
+                    None
+                }
                 key => {
                     let code = code_from_key(*key)?;
-                    unsafe { self.get_code_name(code.into(), 0) }
+                    unsafe { self.translate(code.into()) }
                 }
             },
             EventType::KeyRelease(key) => match key {
@@ -172,6 +214,12 @@ impl KeyboardState for Keyboard {
                     self.last_state[VK_RSHIFT_] &= HIGHBIT;
                     None
                 }
+                Key::AltGr => {
+                    for vk in ALT_GR {
+                        self.last_state[vk] &= !HIGHBIT;
+                    }
+                    None
+                }
                 _ => None,
             },
 
@@ -182,5 +230,10 @@ impl KeyboardState for Keyboard {
     fn reset(&mut self) {
         self.last_state[16] = 0;
         self.last_state[20] = 0;
+        for vk in ALT_GR {
+            self.last_state[vk] = 0;
+        }
+        // Drops a pending dead key.
+        unsafe { self.clear_keyboard_buffer(VK_SPACE as UINT, 0, Self::foreground_layout()) }
     }
 }
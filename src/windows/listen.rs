@@ -1,12 +1,21 @@
-use crate::rdev::{Event, EventType, ListenError};
-use crate::windows::common::{HOOK, HookError, KEYBOARD, convert, set_key_hook, set_mouse_hook};
+use crate::rdev::{Event, EventType, ListenError, ListenHandle};
+use crate::windows::common::{
+    CallbackGuard, HookError, KEYBOARD, convert, get_modifiers, get_raw_code, get_source,
+    get_timestamp, run_message_loop, set_key_hook, set_mouse_hook,
+};
+use std::cell::RefCell;
 use std::os::raw::c_int;
 use std::ptr::null_mut;
 use std::time::SystemTime;
 use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
-use winapi::um::winuser::{CallNextHookEx, GetMessageA, HC_ACTION};
+use winapi::um::winuser::{CallNextHookEx, HC_ACTION};
 
-static mut GLOBAL_CALLBACK: Option<Box<dyn FnMut(Event)>> = None;
+type Callback = Box<dyn FnMut(Event)>;
+
+thread_local! {
+    /// The callback of the hooks installed by this thread.
+    static CALLBACK: RefCell<Option<Callback>> = const { RefCell::new(None) };
+}
 
 impl From<HookError> for ListenError {
     fn from(error: HookError) -> Self {
@@ -23,37 +32,49 @@ unsafe extern "system" fn raw_callback(code: c_int, param: WPARAM, lpdata: LPARA
             let opt = convert(param, lpdata);
             if let Some(event_type) = opt {
                 let name = match &event_type {
-                    EventType::KeyPress(_key) => match (*KEYBOARD).lock() {
-                        Ok(mut keyboard) => keyboard.get_name(lpdata),
-                        Err(_) => None,
-                    },
+                    EventType::KeyPress(_key) | EventType::KeyRepeat(_key) => {
+                        match (*KEYBOARD).lock() {
+                            Ok(mut keyboard) => keyboard.get_name(lpdata),
+                            Err(_) => None,
+                        }
+                    }
                     _ => None,
                 };
+                let modifiers = get_modifiers(&event_type);
                 let event = Event {
                     event_type,
                     time: SystemTime::now(),
+                    timestamp: Some(get_timestamp(param, lpdata)),
                     name,
+                    device: None,
+                    source: get_source(param, lpdata),
+                    modifiers,
+                    raw_code: get_raw_code(param, lpdata),
                 };
-                let ptr = &raw mut GLOBAL_CALLBACK;
-                if let Some(callback) = &mut *ptr {
-                    callback(event);
-                }
+                // Borrowing fails if the callback itself pumps messages.
+                CALLBACK.with(|callback| {
+                    if let Ok(mut callback) = callback.try_borrow_mut()
+                        && let Some(callback) = callback.as_mut()
+                    {
+                        callback(event);
+                    }
+                });
             }
         }
-        CallNextHookEx(HOOK, code, param, lpdata)
+        CallNextHookEx(null_mut(), code, param, lpdata)
     }
 }
 
-pub fn listen<T>(callback: T) -> Result<(), ListenError>
+pub fn listen<T>(callback: T, handle: &ListenHandle) -> Result<(), ListenError>
 where
     T: FnMut(Event) + 'static,
 {
     unsafe {
-        GLOBAL_CALLBACK = Some(Box::new(callback));
-        set_key_hook(raw_callback)?;
-        set_mouse_hook(raw_callback)?;
+        let _callback = CallbackGuard::set(&CALLBACK, Box::new(callback));
+        let _key_hook = set_key_hook(raw_callback)?;
+        let _mouse_hook = set_mouse_hook(raw_callback)?;
 
-        GetMessageA(null_mut(), null_mut(), 0, 0);
+        run_message_loop(handle);
     }
     Ok(())
 }
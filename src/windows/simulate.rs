@@ -1,5 +1,6 @@
-use crate::rdev::{Button, EventType, SimulateError};
-use crate::windows::keycodes::code_from_key;
+use crate::keycodes::windows_vk::code_from_key;
+use crate::rdev::{Button, DisplayError, EventType, SimulateError};
+use crate::windows::common::RDEV_EXTRA_INFO;
 use std::convert::TryFrom;
 use std::mem::size_of;
 use winapi::ctypes::{c_int, c_short};
@@ -11,6 +12,7 @@ use winapi::um::winuser::{
     MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
     MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
     MOUSEEVENTF_XUP, MOUSEINPUT, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SendInput, WHEEL_DELTA,
+    XBUTTON1, XBUTTON2,
 };
 /// Not defined in win32 but define here for clarity
 static KEYEVENTF_KEYDOWN: DWORD = 0;
@@ -24,7 +26,7 @@ fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(),
         mouseData: data,
         dwFlags: flags,
         time: 0,
-        dwExtraInfo: 0,
+        dwExtraInfo: RDEV_EXTRA_INFO,
     };
     let mut input = [INPUT {
         type_: INPUT_MOUSE,
@@ -38,7 +40,7 @@ fn sim_mouse_event(flags: DWORD, data: DWORD, dx: LONG, dy: LONG) -> Result<(),
         )
     };
     if value != 1 {
-        Err(SimulateError)
+        Err(SimulateError::Rejected)
     } else {
         Ok(())
     }
@@ -52,7 +54,7 @@ fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), Simulate
         wScan: scan,
         dwFlags: flags,
         time: 0,
-        dwExtraInfo: 0,
+        dwExtraInfo: RDEV_EXTRA_INFO,
     };
     let mut input = [INPUT {
         type_: INPUT_KEYBOARD,
@@ -66,51 +68,81 @@ fn sim_keyboard_event(flags: DWORD, vk: WORD, scan: WORD) -> Result<(), Simulate
         )
     };
     if value != 1 {
-        Err(SimulateError)
+        Err(SimulateError::Rejected)
     } else {
         Ok(())
     }
 }
 
+/// Wheel data of `clicks`, `WHEEL_DELTA` per click.
+fn wheel_delta(clicks: i64) -> Result<c_short, SimulateError> {
+    c_short::try_from(clicks)
+        .ok()
+        .and_then(|clicks| clicks.checked_mul(WHEEL_DELTA))
+        .ok_or(SimulateError::OutOfRange)
+}
+
 pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
     match event_type {
-        EventType::KeyPress(key) => {
-            let code = code_from_key(*key).ok_or(SimulateError)?;
+        EventType::KeyPress(key) | EventType::KeyRepeat(key) => {
+            let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
             sim_keyboard_event(KEYEVENTF_KEYDOWN, code, 0)
         }
         EventType::KeyRelease(key) => {
-            let code = code_from_key(*key).ok_or(SimulateError)?;
+            let code = code_from_key(*key).ok_or(SimulateError::UnmappedKey(*key))?;
             sim_keyboard_event(KEYEVENTF_KEYUP, code, 0)
         }
         EventType::ButtonPress(button) => match button {
             Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
             Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
             Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
+            Button::Back => sim_mouse_event(MOUSEEVENTF_XDOWN, XBUTTON1.into(), 0, 0),
+            Button::Forward => sim_mouse_event(MOUSEEVENTF_XDOWN, XBUTTON2.into(), 0, 0),
             Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XDOWN, (*code).into(), 0, 0),
         },
         EventType::ButtonRelease(button) => match button {
             Button::Left => sim_mouse_event(MOUSEEVENTF_LEFTUP, 0, 0, 0),
             Button::Middle => sim_mouse_event(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
             Button::Right => sim_mouse_event(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
+            Button::Back => sim_mouse_event(MOUSEEVENTF_XUP, XBUTTON1.into(), 0, 0),
+            Button::Forward => sim_mouse_event(MOUSEEVENTF_XUP, XBUTTON2.into(), 0, 0),
             Button::Unknown(code) => sim_mouse_event(MOUSEEVENTF_XUP, (*code).into(), 0, 0),
         },
         EventType::Wheel { delta_x, delta_y } => {
             if *delta_x != 0 {
-                sim_mouse_event(
-                    MOUSEEVENTF_HWHEEL,
-                    (c_short::try_from(*delta_x).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
-                    0,
-                    0,
-                )?;
+                sim_mouse_event(MOUSEEVENTF_HWHEEL, wheel_delta(*delta_x)? as u32, 0, 0)?;
             }
 
             if *delta_y != 0 {
-                sim_mouse_event(
-                    MOUSEEVENTF_WHEEL,
-                    (c_short::try_from(*delta_y).map_err(|_| SimulateError)? * WHEEL_DELTA) as u32,
-                    0,
-                    0,
-                )?;
+                sim_mouse_event(MOUSEEVENTF_WHEEL, wheel_delta(*delta_y)? as u32, 0, 0)?;
+            }
+            Ok(())
+        }
+        EventType::Swipe { .. }
+        | EventType::Pinch { .. }
+        | EventType::Hold { .. }
+        | EventType::TouchDown { .. }
+        | EventType::TouchMove { .. }
+        | EventType::TouchUp { .. }
+        | EventType::TouchCancel { .. }
+        | EventType::TouchFrame
+        | EventType::TabletProximity { .. }
+        | EventType::TabletTip { .. }
+        | EventType::TabletAxis { .. }
+        | EventType::TabletButton { .. }
+        | EventType::TabletPadButton { .. }
+        | EventType::Switch { .. } => Err(SimulateError::UnsupportedEvent(*event_type)),
+        // Windows takes fractions of WHEEL_DELTA for high resolution scrolling.
+        EventType::Scroll {
+            delta_x, delta_y, ..
+        } => {
+            if *delta_x != 0.0 {
+                let data = (delta_x * WHEEL_DELTA as f64).round() as c_int;
+                sim_mouse_event(MOUSEEVENTF_HWHEEL, data as DWORD, 0, 0)?;
+            }
+            if *delta_y != 0.0 {
+                let data = (delta_y * WHEEL_DELTA as f64).round() as c_int;
+                sim_mouse_event(MOUSEEVENTF_WHEEL, data as DWORD, 0, 0)?;
             }
             Ok(())
         }
@@ -118,7 +150,7 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
             let width = unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) };
             let height = unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) };
             if width == 0 || height == 0 {
-                return Err(SimulateError);
+                return Err(SimulateError::Display(DisplayError::NoDisplay));
             }
 
             sim_mouse_event(
@@ -128,5 +160,9 @@ pub fn simulate(event_type: &EventType) -> Result<(), SimulateError> {
                 (*y as i32 + 1) * 65535 / height,
             )
         }
+        // Without MOUSEEVENTF_ABSOLUTE, dx and dy are relative (and accelerated by Windows).
+        EventType::MouseMoveRelative { dx, dy } => {
+            sim_mouse_event(MOUSEEVENTF_MOVE, 0, dx.round() as LONG, dy.round() as LONG)
+        }
     }
 }
@@ -1,5 +1,8 @@
 use lazy_static::lazy_static;
-use rdev::{Button, Event, EventType, Key, listen, simulate};
+use rdev::{
+    Button, Event, EventSource, EventType, Key, ListenHandle, listen, listen_channel, listen_until,
+    simulate,
+};
 use serial_test::serial;
 use std::error::Error;
 use std::iter::Iterator;
@@ -72,3 +75,53 @@ fn test_listen_and_simulate() -> Result<(), Box<dyn Error>> {
     let mut events = events.chain(click_events);
     sim_then_listen(&mut events)
 }
+
+#[test]
+#[serial]
+fn test_listen_until() -> Result<(), Box<dyn Error>> {
+    let handle = ListenHandle::new();
+    let stopper = handle.clone();
+    let listener = thread::spawn(move || listen_until(|_| (), &handle).is_ok());
+    thread::sleep(Duration::from_millis(500));
+
+    stopper.stop();
+    assert!(listener.join().expect("Listener panicked"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_listen_channel() -> Result<(), Box<dyn Error>> {
+    let events = listen_channel();
+    thread::sleep(Duration::from_millis(500));
+
+    let event_type = EventType::KeyPress(Key::KeyS);
+    simulate(&event_type)?;
+    simulate(&EventType::KeyRelease(Key::KeyS))?;
+    let received = events.recv_timeout(Duration::from_secs(1))?;
+    assert_eq!(received.event_type, event_type);
+    assert_eq!(received.source, EventSource::Rdev);
+    let release = events.recv_timeout(Duration::from_secs(1))?;
+    assert!(received.timestamp.is_some());
+    assert!(release.timestamp >= received.timestamp);
+
+    assert!(events.stop().is_ok());
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+#[serial]
+async fn test_listen_stream() -> Result<(), Box<dyn Error>> {
+    use futures::StreamExt;
+
+    let mut events = rdev::listen_stream();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let event_type = EventType::KeyPress(Key::KeyS);
+    simulate(&event_type)?;
+    simulate(&EventType::KeyRelease(Key::KeyS))?;
+    let received = tokio::time::timeout(Duration::from_secs(1), events.next()).await?;
+    assert_eq!(received.map(|event| event.event_type), Some(event_type));
+    Ok(())
+}